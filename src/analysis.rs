@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use itertools::Itertools;
+
+use crate::{
+    board::{Board, Cell},
+    custom_gizmos::CustomGizmos,
+    graph::{distances, Analysis},
+};
+
+/// Cells listed by number in the analysis window before the rest are summed up.
+const MAX_LISTED: usize = 20;
+
+pub fn analysis_plugin(app: &mut App) {
+    app.init_resource::<BoardAnalysis>();
+    app.add_systems(Update, (analysis_window, draw_highlights));
+}
+
+/// Connectivity of the board being edited, shown in the analysis window. Binaries keep it current
+/// with [`BoardAnalysis::update`].
+#[derive(Resource, Default)]
+pub struct BoardAnalysis {
+    board: Board,
+    analysis: Analysis,
+    /// Cell to measure distances from.
+    from: usize,
+    distances: Vec<Option<usize>>,
+    highlight: Highlight,
+}
+
+/// Tracks whether a board has changed since it was last processed and reports when it's time to
+/// process it again. Drags (painting, moving keyframes, dragging values) change the board every
+/// frame, so slow work like the analysis waits until the left mouse button is let go.
+pub fn settled(stale: &mut bool, changed: bool, mouse: &ButtonInput<MouseButton>) -> bool {
+    *stale |= changed;
+    if *stale && !mouse.pressed(MouseButton::Left) {
+        *stale = false;
+        true
+    } else {
+        false
+    }
+}
+
+/// Which cells are highlighted on the canvas.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum Highlight {
+    #[default]
+    Nothing,
+    Component(usize),
+    Isolated,
+    DeadEnds,
+    Sinks,
+    ArticulationCells,
+    /// Every cell, colored by distance.
+    Distances,
+}
+
+impl BoardAnalysis {
+    pub fn update(&mut self, board: Board) {
+        self.analysis = Analysis::new(&board);
+        self.from = self.from.min(board.cells().len().saturating_sub(1));
+        self.distances = distances(&board, self.from);
+        self.board = board;
+        if matches!(self.highlight, Highlight::Component(i) if i >= self.analysis.components.len())
+        {
+            self.highlight = Highlight::Nothing;
+        }
+    }
+
+    fn highlighted(&self) -> &[usize] {
+        let analysis = &self.analysis;
+        match self.highlight {
+            Highlight::Component(i) => &analysis.components[i],
+            Highlight::Isolated => &analysis.isolated,
+            Highlight::DeadEnds => &analysis.dead_ends,
+            Highlight::Sinks => &analysis.sinks,
+            Highlight::ArticulationCells => &analysis.articulation_cells,
+            Highlight::Nothing | Highlight::Distances => &[],
+        }
+    }
+}
+
+fn list(cells: &[usize]) -> String {
+    let mut text = cells.iter().take(MAX_LISTED).join(", ");
+    if cells.len() > MAX_LISTED {
+        text += &format!(" and {} more", cells.len() - MAX_LISTED);
+    }
+    text
+}
+
+fn analysis_window(mut ui: EguiContexts, mut state: ResMut<BoardAnalysis>) {
+    let state = &mut *state;
+    egui::Window::new("Analysis").show(ui.ctx_mut(), |ui| {
+        let analysis = &state.analysis;
+        let highlight = &mut state.highlight;
+        ui.label(format!("Capacity: {}", analysis.capacity));
+        ui.radio_value(highlight, Highlight::Nothing, "Highlight nothing");
+
+        ui.separator();
+        if analysis.strongly_connected() {
+            ui.label("Every cell can reach every other");
+        } else {
+            ui.label(format!(
+                "{} groups of cells that can't all reach each other",
+                analysis.components.len()
+            ));
+            egui::ScrollArea::vertical()
+                .id_source("components")
+                .max_height(100.0)
+                .show(ui, |ui| {
+                    for (i, cells) in analysis.components.iter().enumerate() {
+                        let text = format!("Group {} ({}): {}", i + 1, cells.len(), list(cells));
+                        ui.radio_value(highlight, Highlight::Component(i), text);
+                    }
+                });
+        }
+
+        ui.separator();
+        for (value, name, cells) in [
+            (Highlight::Isolated, "Isolated", &analysis.isolated),
+            (Highlight::DeadEnds, "Dead ends", &analysis.dead_ends),
+            (Highlight::Sinks, "Sinks", &analysis.sinks),
+            (
+                Highlight::ArticulationCells,
+                "Articulation cells",
+                &analysis.articulation_cells,
+            ),
+        ] {
+            let text = format!("{name} ({}): {}", cells.len(), list(cells));
+            ui.add_enabled_ui(!cells.is_empty(), |ui| {
+                ui.radio_value(highlight, value, text)
+            });
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.radio_value(highlight, Highlight::Distances, "Distances from cell");
+            let last = state.board.cells().len().saturating_sub(1);
+            let from = egui::DragValue::new(&mut state.from).clamp_range(0..=last);
+            if ui.add(from).changed() {
+                state.distances = distances(&state.board, state.from);
+            }
+        });
+        let unreachable = state.distances.iter().filter(|x| x.is_none()).count();
+        if let Some(farthest) = state.distances.iter().flatten().max() {
+            ui.label(format!("Farthest: {farthest} links away"));
+            ui.label(format!("Unreachable: {unreachable} cells"));
+        }
+    });
+}
+
+fn marker_radius(cell: &Cell) -> f32 {
+    let bounds = cell.shape.bounding_box();
+    bounds.map_or(0.1, |x| x.size().min_element() * 0.25)
+}
+
+fn draw_highlights(state: Res<BoardAnalysis>, mut gizmos: Gizmos) {
+    let cells = state.board.cells();
+    if state.highlight == Highlight::Distances {
+        // Blue near the start, through green, to red at the far end
+        let farthest = state.distances.iter().flatten().max().copied().unwrap_or(0);
+        for (cell, distance) in cells.iter().zip(&state.distances) {
+            let color = match *distance {
+                Some(d) => {
+                    let fraction = d as f32 / farthest.max(1) as f32;
+                    Color::hsl(240.0 * (1.0 - fraction), 1.0, 0.5)
+                }
+                None => Color::DARK_GRAY,
+            };
+            gizmos.circle_2d(cell.position, marker_radius(cell), color);
+        }
+        return;
+    }
+    for &i in state.highlighted() {
+        gizmos.polygon(&cells[i].shape, Color::CYAN);
+        gizmos.circle_2d(cells[i].position, marker_radius(&cells[i]), Color::CYAN);
+    }
+}
@@ -1,21 +1,54 @@
 use std::hash::Hash;
 
 use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
 
 use crate::board::Polygon;
 
+/// How a lattice's cells are placed in the world.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    /// Length of each side of a cell.
+    pub size: f32,
+    /// Only hex lattices have more than one orientation; the others ignore this.
+    #[serde(default)]
+    pub orientation: Orientation,
+}
+
+impl Layout {
+    pub const fn new(size: f32) -> Self {
+        Self {
+            size,
+            orientation: Orientation::FlatTop,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Orientation {
+    #[default]
+    FlatTop,
+    PointyTop,
+}
+
 pub trait BaseCell: std::fmt::Debug + Copy + Eq + Hash + Ord {
     type Corner: BaseCorner;
 
-    fn pick(pos: Vec2) -> Self;
-    fn position(&self) -> Vec2;
+    const DEFAULT_LAYOUT: Layout;
+
+    fn pick(pos: Vec2, layout: Layout) -> Self;
+    fn position(&self, layout: Layout) -> Vec2;
     fn neighbors(&self) -> Vec<Self>
     where
         Self: Sized;
-    fn shape(&self) -> Polygon;
     fn corners(&self) -> Vec<Self::Corner>;
     fn lines(&self) -> Vec<Edge<Self>>;
 
+    /// The polygon through [`Self::corners`], which are counter-clockwise.
+    fn shape(&self, layout: Layout) -> Polygon {
+        Polygon::new(self.corners().iter().map(|x| x.position(layout)).collect())
+    }
+
     fn adjacent_to(&self, other: &Self) -> bool
     where
         Self: Sized,
@@ -23,6 +56,41 @@ pub trait BaseCell: std::fmt::Debug + Copy + Eq + Hash + Ord {
         self.neighbors().into_iter().any(|x| x == *other)
     }
 
+    /// Cells that only share a corner with this one, for lattices where those can be linked too.
+    fn diagonal_neighbors(&self) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        Vec::new()
+    }
+
+    /// [`Self::neighbors`], plus [`Self::diagonal_neighbors`] if `diagonals` is set.
+    fn linked_neighbors(&self, diagonals: bool) -> Vec<Self>
+    where
+        Self: Sized,
+    {
+        let mut result = self.neighbors();
+        if diagonals {
+            result.extend(self.diagonal_neighbors());
+        }
+        result
+    }
+
+    fn linked_to(&self, other: &Self, diagonals: bool) -> bool
+    where
+        Self: Sized,
+    {
+        self.linked_neighbors(diagonals).contains(other)
+    }
+
+    fn neighboring_corner(&self, other: &Self) -> Self::Corner {
+        let neighbor_corners = other.corners();
+        self.corners()
+            .into_iter()
+            .find(|x| neighbor_corners.contains(x))
+            .expect("Cells don't share a corner!")
+    }
+
     fn neighboring_edge(&self, other: &Self) -> Edge<Self> {
         let my_edges = self.lines();
         let mut neighbor_edges = other.lines();
@@ -31,43 +99,154 @@ pub trait BaseCell: std::fmt::Debug + Copy + Eq + Hash + Ord {
         }
         my_edges
             .into_iter()
-            .filter(|x| neighbor_edges.contains(x))
-            .next()
+            .find(|x| neighbor_edges.contains(x))
             .expect("Edges are not adjacent!")
     }
 }
 
 pub type Edge<C> = [<C as BaseCell>::Corner; 2];
 
+/// Cells of a lattice that has a grid distance and can be turned and mirrored onto itself about
+/// any cell. Directions are as laid out with a flat-top [`Layout`].
+pub trait LatticeCell: BaseCell {
+    /// How many turns of equal size bring the lattice back onto itself.
+    const ROTATIONS: i32;
+
+    /// Fewest steps between neighbors it takes to get from `self` to `other`.
+    fn distance(&self, other: &Self) -> i32;
+    /// Cells at exactly `radius`, counter-clockwise.
+    fn ring(&self, radius: i32) -> Vec<Self>;
+    /// Cells within `radius`, in no particular order.
+    fn range(&self, radius: i32) -> Vec<Self>;
+    /// Cells on the way from `self` to `other`, both included, each a neighbor of the last.
+    fn line(&self, other: &Self) -> Vec<Self>;
+    /// Turns `steps` times by a `1 / ROTATIONS` turn counter-clockwise about `center`.
+    fn rotate(&self, center: &Self, steps: i32) -> Self;
+    /// Mirrors across the horizontal line through `center`.
+    fn mirror(&self, center: &Self) -> Self;
+
+    /// Rings from `0` out to `radius`, innermost first.
+    fn spiral(&self, radius: i32) -> Vec<Self> {
+        (0..=radius).flat_map(|x| self.ring(x)).collect()
+    }
+
+    /// Mirrors across the line through `center` at `axis` times half of a `1 / ROTATIONS` turn
+    /// counter-clockwise from horizontal.
+    fn reflect(&self, center: &Self, axis: i32) -> Self {
+        self.mirror(center).rotate(center, axis)
+    }
+}
+
 pub trait BaseCorner: std::fmt::Debug + Copy + Eq + Hash + Ord {
-    fn position(&self) -> Vec2;
+    fn position(&self, layout: Layout) -> Vec2;
 }
 
 pub mod square {
     use bevy::math::Vec2;
+    use serde::{Deserialize, Serialize};
 
-    use crate::{board::Polygon, rounding::Rounding};
+    use crate::rounding::Rounding;
 
-    use super::{BaseCell, BaseCorner};
+    use super::{BaseCell, BaseCorner, LatticeCell, Layout};
 
-    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
     pub struct Cell {
         pub x: i32,
         pub y: i32,
     }
 
+    /// Distances are in steps between orthogonal neighbors, so rings are diamonds.
+    impl LatticeCell for Cell {
+        const ROTATIONS: i32 = 4;
+
+        fn distance(&self, other: &Self) -> i32 {
+            (self.x - other.x).abs() + (self.y - other.y).abs()
+        }
+
+        fn ring(&self, radius: i32) -> Vec<Self> {
+            if radius <= 0 {
+                return if radius == 0 { vec![*self] } else { Vec::new() };
+            }
+            let mut cell = Self {
+                x: self.x + radius,
+                y: self.y,
+            };
+            let mut result = Vec::new();
+            for (dx, dy) in [(-1, 1), (-1, -1), (1, -1), (1, 1)] {
+                for _ in 0..radius {
+                    result.push(cell);
+                    cell.x += dx;
+                    cell.y += dy;
+                }
+            }
+            result
+        }
+
+        fn range(&self, radius: i32) -> Vec<Self> {
+            (-radius..=radius)
+                .flat_map(|dx| {
+                    let height = radius - dx.abs();
+                    (-height..=height).map(move |dy| Self {
+                        x: self.x + dx,
+                        y: self.y + dy,
+                    })
+                })
+                .collect()
+        }
+
+        fn line(&self, other: &Self) -> Vec<Self> {
+            let (nx, ny) = ((other.x - self.x).abs(), (other.y - self.y).abs());
+            let (sx, sy) = ((other.x - self.x).signum(), (other.y - self.y).signum());
+            let mut cell = *self;
+            let mut result = vec![cell];
+            let (mut ix, mut iy) = (0, 0);
+            while ix < nx || iy < ny {
+                // Step along whichever axis the straight line crosses a cell boundary on first
+                if (1 + 2 * ix) * ny < (1 + 2 * iy) * nx {
+                    cell.x += sx;
+                    ix += 1;
+                } else {
+                    cell.y += sy;
+                    iy += 1;
+                }
+                result.push(cell);
+            }
+            result
+        }
+
+        fn rotate(&self, center: &Self, steps: i32) -> Self {
+            let (mut x, mut y) = (self.x - center.x, self.y - center.y);
+            for _ in 0..steps.rem_euclid(Self::ROTATIONS) {
+                (x, y) = (-y, x);
+            }
+            Self {
+                x: center.x + x,
+                y: center.y + y,
+            }
+        }
+
+        fn mirror(&self, center: &Self) -> Self {
+            Self {
+                x: self.x,
+                y: 2 * center.y - self.y,
+            }
+        }
+    }
+
     impl BaseCell for Cell {
         type Corner = Corner;
 
-        fn pick(pos: Vec2) -> Self {
+        const DEFAULT_LAYOUT: Layout = Layout::new(2.0);
+
+        fn pick(pos: Vec2, layout: Layout) -> Self {
             Self {
-                x: (pos.x / 2.0).round_to_int(),
-                y: (pos.y / 2.0).round_to_int(),
+                x: (pos.x / layout.size).round_to_int(),
+                y: (pos.y / layout.size).round_to_int(),
             }
         }
 
-        fn position(&self) -> Vec2 {
-            Vec2::new(self.x as f32, self.y as f32) * 2.0
+        fn position(&self, layout: Layout) -> Vec2 {
+            Vec2::new(self.x as f32, self.y as f32) * layout.size
         }
 
         fn neighbors(&self) -> Vec<Self> {
@@ -84,18 +263,13 @@ pub mod square {
             .into()
         }
 
-        fn shape(&self) -> Polygon {
-            Polygon {
-                points: [
-                    Vec2::new(-1.0, -1.0),
-                    Vec2::new(1.0, -1.0),
-                    Vec2::new(1.0, 1.0),
-                    Vec2::new(-1.0, 1.0),
-                ]
-                .iter()
-                .map(|&x| x + self.position())
-                .collect(),
-            }
+        fn diagonal_neighbors(&self) -> Vec<Self> {
+            [(1, 1), (-1, 1), (-1, -1), (1, -1)]
+                .map(|(dx, dy)| Self {
+                    x: self.x + dx,
+                    y: self.y + dy,
+                })
+                .into()
         }
 
         fn corners(&self) -> Vec<Corner> {
@@ -139,20 +313,41 @@ pub mod square {
     }
 
     impl BaseCorner for Corner {
-        fn position(&self) -> Vec2 {
-            Vec2::new(self.x as f32, self.y as f32) * 2.0 - Vec2::ONE
+        fn position(&self, layout: Layout) -> Vec2 {
+            (Vec2::new(self.x as f32, self.y as f32) - 0.5) * layout.size
         }
     }
 }
 
 pub mod hex {
+    use std::f32::consts::FRAC_PI_6;
+
     use bevy::math::Vec2;
+    use serde::{Deserialize, Serialize};
 
-    use crate::{board::Polygon, rounding::Rounding};
+    use crate::rounding::Rounding;
 
-    use super::{BaseCell, BaseCorner};
+    use super::{BaseCell, BaseCorner, LatticeCell, Layout, Orientation};
 
-    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+    /// Turns a position in a flat-top layout of size 1 into world space. Pointy-top hexes are
+    /// flat-top ones turned 30° clockwise.
+    fn to_world(pos: Vec2, layout: Layout) -> Vec2 {
+        let pos = pos * layout.size;
+        match layout.orientation {
+            Orientation::FlatTop => pos,
+            Orientation::PointyTop => Vec2::from_angle(-FRAC_PI_6).rotate(pos),
+        }
+    }
+
+    fn from_world(pos: Vec2, layout: Layout) -> Vec2 {
+        let pos = match layout.orientation {
+            Orientation::FlatTop => pos,
+            Orientation::PointyTop => Vec2::from_angle(FRAC_PI_6).rotate(pos),
+        };
+        pos / layout.size
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
     pub struct Cell {
         pub q: i32,
         pub r: i32,
@@ -161,6 +356,8 @@ pub mod hex {
     impl BaseCell for Cell {
         type Corner = Corner;
 
+        const DEFAULT_LAYOUT: Layout = Layout::new(1.0);
+
         fn corners(&self) -> Vec<Self::Corner> {
             [
                 Corner {
@@ -240,43 +437,117 @@ pub mod hex {
             .into()
         }
 
-        fn pick(pos: Vec2) -> Self {
+        fn pick(pos: Vec2, layout: Layout) -> Self {
+            let pos = from_world(pos, layout);
             let cq = 2.0 * pos.x / 3.0;
             let cr = -1.0 / 3.0 * pos.x + 3.0f32.sqrt() / 3.0 * pos.y;
-            let (q, dq) = cq.round_with_diff();
-            let (r, dr) = cr.round_with_diff();
-            let (s, ds) = (-cq - cr).round_with_diff();
+            Self::round(cq, cr)
+        }
+
+        fn position(&self, layout: Layout) -> Vec2 {
+            to_world(self.flat_position(), layout)
+        }
+    }
+
+    /// Neighbor offsets, counter-clockwise from the one at 30°.
+    const DIRECTIONS: [(i32, i32); 6] = [(1, 0), (0, 1), (-1, 1), (-1, 0), (0, -1), (1, -1)];
+
+    impl LatticeCell for Cell {
+        const ROTATIONS: i32 = 6;
+
+        fn distance(&self, other: &Self) -> i32 {
+            let (dq, dr) = (self.q - other.q, self.r - other.r);
+            (dq.abs() + dr.abs() + (dq + dr).abs()) / 2
+        }
+
+        fn ring(&self, radius: i32) -> Vec<Self> {
+            if radius <= 0 {
+                return if radius == 0 { vec![*self] } else { Vec::new() };
+            }
+            let mut cell = Self {
+                q: self.q,
+                r: self.r - radius,
+            };
+            let mut result = Vec::new();
+            for (dq, dr) in DIRECTIONS {
+                for _ in 0..radius {
+                    result.push(cell);
+                    cell.q += dq;
+                    cell.r += dr;
+                }
+            }
+            result
+        }
+
+        fn range(&self, radius: i32) -> Vec<Self> {
+            (-radius..=radius)
+                .flat_map(|dq| {
+                    let rs = (-radius).max(-dq - radius)..=radius.min(-dq + radius);
+                    rs.map(move |dr| Self {
+                        q: self.q + dq,
+                        r: self.r + dr,
+                    })
+                })
+                .collect()
+        }
+
+        fn line(&self, other: &Self) -> Vec<Self> {
+            let n = self.distance(other);
+            if n == 0 {
+                return vec![*self];
+            }
+            // Nudged so points exactly between two cells always round the same way
+            let (q1, r1) = (self.q as f32 + 1e-3, self.r as f32 + 2e-3);
+            let (q2, r2) = (other.q as f32 + 1e-3, other.r as f32 + 2e-3);
+            (0..=n)
+                .map(|i| {
+                    let t = i as f32 / n as f32;
+                    Self::round(q1 + (q2 - q1) * t, r1 + (r2 - r1) * t)
+                })
+                .collect()
+        }
+
+        fn rotate(&self, center: &Self, steps: i32) -> Self {
+            let (mut q, mut r) = (self.q - center.q, self.r - center.r);
+            for _ in 0..steps.rem_euclid(Self::ROTATIONS) {
+                (q, r) = (-r, q + r);
+            }
+            Self {
+                q: center.q + q,
+                r: center.r + r,
+            }
+        }
+
+        fn mirror(&self, center: &Self) -> Self {
+            let (q, r) = (self.q - center.q, self.r - center.r);
+            Self {
+                q: center.q + q,
+                r: center.r - q - r,
+            }
+        }
+    }
+
+    impl Cell {
+        /// The cell containing fractional axial coordinates.
+        fn round(q: f32, r: f32) -> Self {
+            let (rq, dq) = q.round_with_diff();
+            let (rr, dr) = r.round_with_diff();
+            let (rs, ds) = (-q - r).round_with_diff();
             if dq > dr && dq > ds {
-                Self { q: -r - s, r }
+                Self { q: -rr - rs, r: rr }
             } else if dr > ds {
-                Self { q, r: -q - s }
+                Self { q: rq, r: -rq - rs }
             } else {
-                Self { q, r }
+                Self { q: rq, r: rr }
             }
         }
 
-        fn position(&self) -> Vec2 {
+        fn flat_position(&self) -> Vec2 {
             Vec2::new(
                 self.q as f32 * 3.0 / 2.0,
                 3.0f32.sqrt() / 2.0 * self.q as f32 + 3.0f32.sqrt() * self.r as f32,
             )
         }
-
-        fn shape(&self) -> Polygon {
-            Polygon {
-                points: [
-                    Vec2::new(1.0, 0.0),
-                    Vec2::new(0.5, 0.866),
-                    Vec2::new(-0.5, 0.866),
-                    Vec2::new(-1.0, 0.0),
-                    Vec2::new(-0.5, -0.866),
-                    Vec2::new(0.5, -0.866),
-                ]
-                .iter()
-                .map(|&x| x + self.position())
-                .collect(),
-            }
-        }
     }
 
     #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
@@ -287,9 +558,281 @@ pub mod hex {
     }
 
     impl BaseCorner for Corner {
-        fn position(&self) -> Vec2 {
+        fn position(&self, layout: Layout) -> Vec2 {
             let &Corner { q, r, left } = self;
-            Cell { q, r }.position() + if left { Vec2::NEG_X } else { Vec2::X }
+            let offset = if left { Vec2::NEG_X } else { Vec2::X };
+            to_world(Cell { q, r }.flat_position() + offset, layout)
+        }
+    }
+}
+
+pub mod triangle {
+    use bevy::math::Vec2;
+    use serde::{Deserialize, Serialize};
+
+    use super::{BaseCell, BaseCorner, Layout};
+
+    /// Height of a triangle with sides of length 1.
+    pub const HEIGHT: f32 = 0.866_025_4;
+
+    /// Cells alternate pointing up and down along each row. `x` counts half-sides, so the cells in
+    /// a row are at `x, x + 1, ...` and those with `x + y` even point up.
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Serialize, Deserialize)]
+    pub struct Cell {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    impl Cell {
+        pub fn points_up(&self) -> bool {
+            (self.x + self.y).rem_euclid(2) == 0
+        }
+    }
+
+    impl BaseCell for Cell {
+        type Corner = Corner;
+
+        const DEFAULT_LAYOUT: Layout = Layout::new(2.0);
+
+        fn pick(pos: Vec2, layout: Layout) -> Self {
+            let y = (pos.y / (HEIGHT * layout.size)).floor() as i32;
+            let x = (pos.x / (0.5 * layout.size)).round() as i32;
+            [x, x - 1, x + 1]
+                .map(|x| Self { x, y })
+                .into_iter()
+                .find(|x| x.shape(layout).contains(pos))
+                .unwrap_or(Self { x, y })
+        }
+
+        fn position(&self, layout: Layout) -> Vec2 {
+            let offset = if self.points_up() { 1.0 } else { 2.0 } / 3.0;
+            Vec2::new(0.5 * self.x as f32, (self.y as f32 + offset) * HEIGHT) * layout.size
+        }
+
+        fn neighbors(&self) -> Vec<Self> {
+            let vertical = if self.points_up() { -1 } else { 1 };
+            [(-1, 0), (1, 0), (0, vertical)]
+                .map(|(dx, dy)| Self {
+                    x: self.x + dx,
+                    y: self.y + dy,
+                })
+                .into()
+        }
+
+        fn corners(&self) -> Vec<Corner> {
+            let Self { x, y } = *self;
+            if self.points_up() {
+                [(x - 1, y), (x + 1, y), (x, y + 1)]
+            } else {
+                [(x, y), (x + 1, y + 1), (x - 1, y + 1)]
+            }
+            .map(|(x, y)| Corner { x, y })
+            .into()
+        }
+
+        fn lines(&self) -> Vec<[Self::Corner; 2]> {
+            let corners = self.corners();
+            [
+                [corners[0], corners[1]],
+                [corners[1], corners[2]],
+                [corners[2], corners[0]],
+            ]
+            .into()
+        }
+    }
+
+    /// Corners sit at `(x / 2, y * HEIGHT)` times the cell size, where `x + y` is always odd.
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+    pub struct Corner {
+        pub x: i32,
+        pub y: i32,
+    }
+
+    impl BaseCorner for Corner {
+        fn position(&self, layout: Layout) -> Vec2 {
+            Vec2::new(0.5 * self.x as f32, self.y as f32 * HEIGHT) * layout.size
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{HashMap, HashSet},
+        f32::consts::{PI, TAU},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_triangle_neighbors_share_edges() {
+        for cell in [
+            triangle::Cell { x: 0, y: 0 },
+            triangle::Cell { x: 3, y: -2 },
+        ] {
+            for neighbor in cell.neighbors() {
+                assert!(neighbor.adjacent_to(&cell));
+                cell.neighboring_edge(&neighbor);
+            }
+        }
+    }
+
+    fn assert_pick_round_trips<C: BaseCell>(cells: impl Iterator<Item = C>, layout: Layout) {
+        for cell in cells {
+            assert_eq!(C::pick(cell.position(layout), layout), cell, "{layout:?}");
+            assert!(cell.shape(layout).is_ccw());
+        }
+    }
+
+    #[test]
+    fn test_square_diagonals() {
+        let cell = square::Cell { x: 0, y: 0 };
+        for neighbor in cell.diagonal_neighbors() {
+            assert!(!cell.adjacent_to(&neighbor));
+            assert!(cell.linked_to(&neighbor, true));
+            assert!(!cell.linked_to(&neighbor, false));
+            assert_eq!(
+                cell.neighboring_corner(&neighbor),
+                neighbor.neighboring_corner(&cell)
+            );
+        }
+        assert!(hex::Cell { q: 0, r: 0 }.diagonal_neighbors().is_empty());
+    }
+
+    #[test]
+    fn test_pick() {
+        let coords = || (-3..3).flat_map(|x| (-3..3).map(move |y| (x, y)));
+        for size in [0.5, 1.0, 2.0, 3.7] {
+            let layout = Layout::new(size);
+            let pointy = Layout {
+                orientation: Orientation::PointyTop,
+                ..layout
+            };
+            assert_pick_round_trips(coords().map(|(x, y)| square::Cell { x, y }), layout);
+            assert_pick_round_trips(coords().map(|(x, y)| triangle::Cell { x, y }), layout);
+            for layout in [layout, pointy] {
+                assert_pick_round_trips(coords().map(|(q, r)| hex::Cell { q, r }), layout);
+            }
         }
     }
+
+    /// Checks every algorithm against breadth-first search and world-space geometry for all cells
+    /// near `center`.
+    fn check_lattice<C: LatticeCell>(center: C, ring_size: impl Fn(i32) -> usize) {
+        const RADIUS: i32 = 6;
+        let mut steps = HashMap::from([(center, 0)]);
+        let mut frontier = vec![center];
+        for step in 1..=RADIUS {
+            frontier = frontier
+                .iter()
+                .flat_map(|x| x.neighbors())
+                .filter(|x| !steps.contains_key(x))
+                .collect();
+            for &x in &frontier {
+                steps.insert(x, step);
+            }
+        }
+        let within = |radius: i32| {
+            let mut x = steps
+                .iter()
+                .filter(|x| *x.1 <= radius)
+                .map(|x| *x.0)
+                .collect::<Vec<_>>();
+            x.sort();
+            x
+        };
+        let sorted = |mut x: Vec<C>| {
+            x.sort();
+            x
+        };
+
+        for (&cell, &step) in &steps {
+            assert_eq!(center.distance(&cell), step, "{cell:?}");
+            assert_eq!(cell.distance(&center), step, "{cell:?}");
+        }
+        assert!(center.ring(-1).is_empty());
+        for radius in 0..=RADIUS {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), ring_size(radius));
+            assert!(ring.iter().all(|x| steps[x] == radius), "{ring:?}");
+            assert_eq!(ring.iter().collect::<HashSet<_>>().len(), ring.len());
+            // Counter-clockwise, so each cell is less than a half turn on from the last
+            let layout = C::DEFAULT_LAYOUT;
+            let angle = |x: &C| {
+                let offset = x.position(layout) - center.position(layout);
+                offset.y.atan2(offset.x)
+            };
+            for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                let turn = (angle(b) - angle(a)).rem_euclid(TAU);
+                assert!(radius == 0 || (turn > 0.0 && turn < PI), "{a:?} {b:?}");
+            }
+            assert_eq!(sorted(center.range(radius)), within(radius));
+            assert_eq!(sorted(center.spiral(radius)), within(radius));
+        }
+
+        let nearby = within(3);
+        for a in &nearby {
+            for b in &nearby {
+                let line = a.line(b);
+                assert_eq!(line.len() as i32, a.distance(b) + 1, "{a:?} {b:?}");
+                assert_eq!((line[0], line[line.len() - 1]), (*a, *b));
+                assert!(line.windows(2).all(|x| x[0].adjacent_to(&x[1])), "{line:?}");
+            }
+        }
+
+        let layout = C::DEFAULT_LAYOUT;
+        let origin = center.position(layout);
+        let turn = TAU / C::ROTATIONS as f32;
+        let assert_near = |cell: C, expected: Vec2| {
+            assert!(
+                (cell.position(layout) - expected).length() < 1e-3,
+                "{cell:?}"
+            );
+        };
+        for &cell in &nearby {
+            let offset = cell.position(layout) - origin;
+            assert_eq!(cell.rotate(&center, C::ROTATIONS), cell);
+            assert_eq!(cell.rotate(&center, -1).rotate(&center, 1), cell);
+            for steps in 0..C::ROTATIONS {
+                let rotation = Vec2::from_angle(turn * steps as f32);
+                assert_near(
+                    cell.rotate(&center, steps),
+                    origin + rotation.rotate(offset),
+                );
+                let axis = Vec2::from_angle(turn * steps as f32 / 2.0);
+                let reflected = 2.0 * offset.dot(axis) * axis - offset;
+                assert_near(cell.reflect(&center, steps), origin + reflected);
+                assert_eq!(cell.reflect(&center, steps).reflect(&center, steps), cell);
+            }
+        }
+    }
+
+    #[test]
+    fn test_square_algorithms() {
+        check_lattice(square::Cell { x: 2, y: -1 }, |r| (4 * r).max(1) as usize);
+    }
+
+    #[test]
+    fn test_hex_algorithms() {
+        check_lattice(hex::Cell { q: -1, r: 3 }, |r| (6 * r).max(1) as usize);
+    }
+
+    #[test]
+    fn test_square_line_is_straight() {
+        let line = square::Cell { x: 0, y: 0 }.line(&square::Cell { x: 4, y: 2 });
+        let xs = line.iter().map(|x| (x.x, x.y)).collect::<Vec<_>>();
+        assert_eq!(xs, [(0, 0), (1, 0), (1, 1), (2, 1), (3, 1), (3, 2), (4, 2)]);
+    }
+
+    #[test]
+    fn test_pointy_hex() {
+        let layout = Layout {
+            size: 1.0,
+            orientation: Orientation::PointyTop,
+        };
+        let shape = hex::Cell { q: 0, r: 0 }.shape(layout);
+        // A corner straight up, and none out to the sides
+        assert!(shape.points.iter().any(|x| (*x - Vec2::Y).length() < 1e-5));
+        assert!(shape.points.iter().all(|x| x.x.abs() < 0.9));
+    }
 }
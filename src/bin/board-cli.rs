@@ -0,0 +1,617 @@
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+    process::ExitCode,
+};
+
+use bevy::math::{Rect, Vec2, Vec3, Vec3Swizzles};
+use grid_builder::{
+    board::{Board, BoardColor, BoardMesh, Mesh},
+    error::{Error, Result},
+    format::BoardFile,
+    import::import_file,
+    validate::{validate, Severity},
+};
+use itertools::Itertools;
+
+const USAGE: &str = "\
+Usage: board-cli <command> [options]
+
+Commands:
+  import <file.glb|file.gltf> [--board <name>] [-o <out.json>]
+      Build a board from a glTF line mesh. Every mesh in the file is kept as decoration.
+  validate <board> [--strict]
+      Check that a board loads and hangs together. Exits with 1 if there are errors, or with
+      --strict, warnings.
+  stats <board>
+      Print cell, link and mesh counts.
+  convert <input> <output>
+      Convert between formats, picked by extension: glTF and board JSON of any version in,
+      board JSON or SVG out.
+  render <board> [-o <out.svg>] [--width <pixels>] [--neighbors]
+      Draw a board to an SVG image.
+
+Boards can be board JSON files of any supported version, or glTF files. Without -o, output goes
+to stdout.";
+
+/// Default width of rendered images, in pixels.
+const RENDER_WIDTH: f32 = 1024.0;
+
+enum Failure {
+    /// Bad arguments. Exits with 2, after printing the usage.
+    Usage(String),
+    Error(Error),
+}
+
+impl From<Error> for Failure {
+    fn from(value: Error) -> Self {
+        Self::Error(value)
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(value: std::io::Error) -> Self {
+        Self::Error(value.into())
+    }
+}
+
+impl From<serde_json::Error> for Failure {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Error(value.into())
+    }
+}
+
+fn main() -> ExitCode {
+    run(std::env::args().skip(1).collect(), &mut std::io::stdout())
+}
+
+/// Runs the command in `args`, printing its output to `out` and problems to stderr.
+fn run(args: Vec<String>, out: &mut impl Write) -> ExitCode {
+    match execute(args, out) {
+        Ok(code) => code,
+        Err(Failure::Usage(message)) => {
+            eprintln!("{message}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(Failure::Error(e)) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn execute(mut args: Vec<String>, out: &mut impl Write) -> std::result::Result<ExitCode, Failure> {
+    if args.is_empty() {
+        return Err(Failure::Usage("No command given".into()));
+    }
+    let command = args.remove(0);
+    match command.as_str() {
+        "import" => {
+            let name = take_option(&mut args, &["--board"])?;
+            let output = take_option(&mut args, &["-o", "--output"])?;
+            let [input] = positional(args)?;
+            let board = import(Path::new(&input), name.as_deref())?;
+            write_output(output.as_deref(), &BoardFile::new(board).to_json()?, out)?;
+        }
+        "validate" => {
+            let strict = take_flag(&mut args, "--strict");
+            let [input] = positional(args)?;
+            let diagnostics = validate(&load(Path::new(&input))?);
+            for diagnostic in &diagnostics {
+                writeln!(out, "{diagnostic}")?;
+            }
+            let errors = diagnostics
+                .iter()
+                .filter(|x| x.severity == Severity::Error)
+                .count();
+            if !diagnostics.is_empty() {
+                eprintln!("{errors} errors, {} warnings", diagnostics.len() - errors);
+            }
+            if errors > 0 || (strict && !diagnostics.is_empty()) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        "stats" => {
+            let [input] = positional(args)?;
+            write!(out, "{}", stats(&load(Path::new(&input))?))?;
+        }
+        "convert" => {
+            let [input, output] = positional(args)?;
+            let board = load(Path::new(&input))?;
+            let contents = match extension(Path::new(&output)).as_str() {
+                "json" => BoardFile::new(board).to_json()?,
+                "svg" => render_svg(&board, RENDER_WIDTH, false),
+                x => return Err(Failure::Usage(format!("Can't convert to .{x} files"))),
+            };
+            write_output(Some(&output), &contents, out)?;
+        }
+        "render" => {
+            let output = take_option(&mut args, &["-o", "--output"])?;
+            let width = match take_option(&mut args, &["--width"])? {
+                Some(x) => x
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|x| *x > 0.0)
+                    .ok_or_else(|| Failure::Usage(format!("Invalid width {x}")))?,
+                None => RENDER_WIDTH,
+            };
+            let neighbors = take_flag(&mut args, "--neighbors");
+            let [input] = positional(args)?;
+            let board = load(Path::new(&input))?;
+            write_output(
+                output.as_deref(),
+                &render_svg(&board, width, neighbors),
+                out,
+            )?;
+        }
+        "help" | "-h" | "--help" => writeln!(out, "{USAGE}")?,
+        x => return Err(Failure::Usage(format!("Unknown command {x}"))),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Removes `--name value` (under any of `names`) from `args`.
+fn take_option(
+    args: &mut Vec<String>,
+    names: &[&str],
+) -> std::result::Result<Option<String>, Failure> {
+    let Some(i) = args.iter().position(|x| names.contains(&x.as_str())) else {
+        return Ok(None);
+    };
+    if i + 1 >= args.len() {
+        return Err(Failure::Usage(format!("{} needs a value", args[i])));
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
+
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let found = args.iter().position(|x| x == name);
+    if let Some(i) = found {
+        args.remove(i);
+    }
+    found.is_some()
+}
+
+/// The arguments left once options are taken out, which must be exactly `N`.
+fn positional<const N: usize>(args: Vec<String>) -> std::result::Result<[String; N], Failure> {
+    if let Some(x) = args.iter().find(|x| x.starts_with('-')) {
+        return Err(Failure::Usage(format!("Unknown option {x}")));
+    }
+    let count = args.len();
+    args.try_into()
+        .map_err(|_| Failure::Usage(format!("Expected {N} arguments, got {count}")))
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|x| x.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Loads a board JSON file, or imports the first board of a glTF file.
+fn load(path: &Path) -> std::result::Result<Board, Failure> {
+    match extension(path).as_str() {
+        "glb" | "gltf" => import(path, None),
+        _ => Ok(BoardFile::from_reader(BufReader::new(File::open(path)?))?.board),
+    }
+}
+
+/// Builds a board from the line mesh named `name`, or the first one, decorated with every mesh in
+/// the file. Problems with the line mesh are printed as warnings. Naming a line mesh the file
+/// doesn't have is a usage error.
+fn import(path: &Path, name: Option<&str>) -> std::result::Result<Board, Failure> {
+    let import = import_file(path)?;
+    for diagnostic in &import.diagnostics {
+        eprintln!("warning: {diagnostic}");
+    }
+    let imported = match name {
+        Some(name) => import.boards.iter().find(|x| x.name == name),
+        None => import.boards.first(),
+    };
+    let Some(imported) = imported else {
+        return Err(match name {
+            Some(name) if !import.boards.is_empty() => {
+                let names = import.boards.iter().map(|x| &x.name).join(", ");
+                Failure::Usage(format!("No line mesh named {name}; pick one of {names}"))
+            }
+            _ => Error::GltfStructure("no line meshes to build a board from".into()).into(),
+        });
+    };
+    if name.is_none() && import.boards.len() > 1 {
+        let names = import.boards.iter().map(|x| &x.name).join(", ");
+        eprintln!(
+            "warning: using {}; pick one of {names} with --board",
+            imported.name
+        );
+    }
+    for diagnostic in &imported.diagnostics {
+        eprintln!("warning: {}: {diagnostic}", imported.name);
+    }
+    let meshes = import
+        .meshes
+        .into_iter()
+        .map(|x| BoardMesh {
+            color: BoardColor::PlayerColor,
+            mesh: x.mesh,
+        })
+        .collect();
+    Ok(Board::new(imported.cells.clone(), meshes))
+}
+
+fn write_output(path: Option<&str>, contents: &str, out: &mut impl Write) -> Result<()> {
+    match path {
+        Some(path) => std::fs::write(path, contents)?,
+        None => writeln!(out, "{contents}")?,
+    }
+    Ok(())
+}
+
+fn stats(board: &Board) -> String {
+    let cells = board.cells();
+    let links = cells.iter().map(|x| x.neighbors.len()).sum::<usize>();
+    let one_way = cells
+        .iter()
+        .enumerate()
+        .flat_map(|(i, x)| x.neighbors.keys().map(move |&n| (i, n)))
+        .filter(|&(i, n)| cells.get(n).is_some_and(|x| !x.neighbors.contains_key(&i)))
+        .count();
+    let degrees = cells
+        .iter()
+        .map(|x| x.neighbors.len())
+        .minmax()
+        .into_option();
+    let capacity = cells
+        .iter()
+        .map(|x| x.neighbors.len().max(1) - 1)
+        .sum::<usize>();
+    let area = cells.iter().map(|x| x.shape.area()).sum::<f32>();
+    let (lines, triangles) = board.meshes.iter().fold((0, 0), |(l, t), x| match &x.mesh {
+        Mesh::IndexedLineMesh { lines, .. } => (l + lines.len(), t),
+        Mesh::IndexedTriMesh { triangles, .. } => (l, t + triangles.len()),
+    });
+
+    let mut out = String::new();
+    writeln!(out, "cells: {}", cells.len()).unwrap();
+    writeln!(out, "links: {links} ({one_way} one-way)").unwrap();
+    if let Some((min, max)) = degrees {
+        writeln!(out, "neighbors per cell: {min} to {max}").unwrap();
+    }
+    writeln!(out, "capacity: {capacity}").unwrap();
+    writeln!(out, "total area: {area}").unwrap();
+    if let Some(bounds) = bounds(board) {
+        writeln!(out, "bounds: {} to {}", bounds.min, bounds.max).unwrap();
+    }
+    writeln!(
+        out,
+        "meshes: {} ({lines} lines, {triangles} triangles)",
+        board.meshes.len()
+    )
+    .unwrap();
+    out
+}
+
+/// Box around every cell and mesh vertex.
+fn bounds(board: &Board) -> Option<Rect> {
+    let cells = board
+        .cells()
+        .iter()
+        .flat_map(|x| x.shape.points.iter().copied().chain([x.position]));
+    let meshes = board.meshes.iter().flat_map(|x| match &x.mesh {
+        Mesh::IndexedLineMesh { vertices, .. } | Mesh::IndexedTriMesh { vertices, .. } => {
+            vertices.iter().map(|x| x.xy())
+        }
+    });
+    cells
+        .chain(meshes)
+        .map(|x| Rect::from_corners(x, x))
+        .reduce(|a, b| a.union(b))
+}
+
+/// Draws cells, meshes, and optionally neighbor paths, `width` pixels wide. Player-colored meshes
+/// are drawn grey.
+fn render_svg(board: &Board, width: f32, neighbors: bool) -> String {
+    let bounds = bounds(board).unwrap_or(Rect::new(0.0, 0.0, 1.0, 1.0));
+    let margin = bounds.size().max_element().max(f32::EPSILON) * 0.05;
+    let bounds = bounds.inset(margin);
+    let scale = width / bounds.width();
+    let height = bounds.height() * scale;
+    // SVG's y axis points down
+    let to_svg = |x: Vec2| Vec2::new(x.x - bounds.min.x, bounds.max.y - x.y) * scale;
+    let path_data = |points: &[Vec2]| {
+        let points = points.iter().map(|&x| to_svg(x));
+        format!(
+            "M {}",
+            points.map(|x| format!("{} {}", x.x, x.y)).join(" L ")
+        )
+    };
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    for mesh in &board.meshes {
+        let color = match mesh.color {
+            BoardColor::PlayerColor => "rgb(128,128,128)".into(),
+            BoardColor::StaticColor(r, g, b) => {
+                let [r, g, b] = [r, g, b].map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8);
+                format!("rgb({r},{g},{b})")
+            }
+        };
+        // Out of range indices are skipped; `validate` reports them
+        let points = |indices: &[usize], vertices: &[Vec3]| {
+            let points = indices.iter().map(|&i| vertices.get(i).map(|x| x.xy()));
+            points.collect::<Option<Vec<_>>>()
+        };
+        match &mesh.mesh {
+            Mesh::IndexedLineMesh { vertices, lines } => {
+                let lines = lines
+                    .iter()
+                    .filter_map(|x| points(x, vertices))
+                    .map(|x| path_data(&x))
+                    .join(" ");
+                writeln!(out, r#"<path d="{lines}" fill="none" stroke="{color}"/>"#).unwrap();
+            }
+            Mesh::IndexedTriMesh {
+                vertices,
+                triangles,
+            } => {
+                let triangles = triangles
+                    .iter()
+                    .filter_map(|x| points(x, vertices))
+                    .map(|x| path_data(&x) + " Z")
+                    .join(" ");
+                writeln!(out, r#"<path d="{triangles}" fill="{color}"/>"#).unwrap();
+            }
+        }
+    }
+    for cell in board.cells().iter().filter(|x| !x.shape.points.is_empty()) {
+        let rings = cell.shape.rings().map(|x| path_data(x) + " Z").join(" ");
+        writeln!(
+            out,
+            r#"<path d="{rings}" fill="rgb(74,144,217)" fill-opacity="0.25" fill-rule="evenodd" stroke="rgb(40,40,40)"/>"#
+        )
+        .unwrap();
+        let center = to_svg(cell.position);
+        writeln!(
+            out,
+            r#"<circle cx="{}" cy="{}" r="2" fill="rgb(40,40,40)"/>"#,
+            center.x, center.y
+        )
+        .unwrap();
+    }
+    if neighbors {
+        for (_, path) in board.cells().iter().flat_map(|x| &x.neighbors) {
+            let points = path
+                .resample(16)
+                .into_iter()
+                .map(to_svg)
+                .map(|x| format!("{},{}", x.x, x.y))
+                .join(" ");
+            writeln!(
+                out,
+                r#"<polyline points="{points}" fill="none" stroke="rgb(255,140,0)"/>"#
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("</svg>");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use grid_builder::board::{Cell, Path, Polygon};
+
+    use super::*;
+
+    fn board() -> Board {
+        let square = |x: f32| {
+            Polygon::new(vec![
+                Vec2::new(x, 0.0),
+                Vec2::new(x + 1.0, 0.0),
+                Vec2::new(x + 1.0, 1.0),
+                Vec2::new(x, 1.0),
+            ])
+        };
+        let (a, b) = (Vec2::new(0.5, 0.5), Vec2::new(1.5, 0.5));
+        let cells = vec![
+            Cell {
+                neighbors: [(1, Path::simple(a, b))].into(),
+                shape: square(0.0),
+                position: a,
+            },
+            Cell {
+                neighbors: [(1, Path::simple(b, b)), (5, Path::simple(b, a))].into(),
+                shape: square(1.0),
+                position: b,
+            },
+        ];
+        Board::new(cells, Vec::new())
+    }
+
+    #[test]
+    fn test_render() {
+        let svg = render_svg(&board(), 200.0, true);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<polyline").count(), 3);
+    }
+
+    /// Two cells linked both ways, or one way with broken links out of the second cell if `broken`.
+    fn board_file(name: &str, broken: bool) -> String {
+        let mut board = board();
+        if !broken {
+            let (a, b) = (board.cells()[0].position, board.cells()[1].position);
+            board.cells_mut()[1].neighbors = [(0, Path::simple(b, a))].into();
+        }
+        let path = temp(name);
+        std::fs::write(&path, BoardFile::new(board).to_json().unwrap()).unwrap();
+        path
+    }
+
+    /// A path in the temp directory, unique to this process.
+    fn temp(name: &str) -> String {
+        let name = format!("board-cli-{}-{name}", std::process::id());
+        std::env::temp_dir()
+            .join(name)
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Runs the CLI on `args`, returning its exit code and what it printed to stdout.
+    fn run_cli(args: &[&str]) -> (ExitCode, String) {
+        let mut out = Vec::new();
+        let code = run(args.iter().map(|x| x.to_string()).collect(), &mut out);
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        let usage = ExitCode::from(2);
+        assert_eq!(run_cli(&[]).0, usage);
+        assert_eq!(run_cli(&["frobnicate"]).0, usage);
+        assert_eq!(run_cli(&["stats"]).0, usage);
+        assert_eq!(run_cli(&["stats", "a.json", "--bogus"]).0, usage);
+        assert_eq!(run_cli(&["render", "a.json", "--width", "-3"]).0, usage);
+        assert_eq!(run_cli(&["render", "a.json", "--width"]).0, usage);
+        // Files that won't load are errors, not usage errors
+        let missing = temp("missing.json");
+        assert_eq!(run_cli(&["stats", &missing]).0, ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn test_run_validate() {
+        let broken = board_file("broken.json", true);
+        let (code, out) = run_cli(&["validate", &broken]);
+        assert_eq!(code, ExitCode::FAILURE);
+        assert_eq!(
+            out,
+            "error: cell 1: Links to itself (Remove the link)\n\
+             error: cell 1: Links to missing cell 5 (Remove the link)\n"
+        );
+        std::fs::remove_file(&broken).unwrap();
+
+        let clean = board_file("clean.json", false);
+        assert_eq!(
+            run_cli(&["validate", &clean]),
+            (ExitCode::SUCCESS, "".into())
+        );
+        assert_eq!(
+            run_cli(&["validate", "--strict", &clean]).0,
+            ExitCode::SUCCESS
+        );
+        // Turning a cell inside out is only a warning
+        let mut board = BoardFile::from_reader(BufReader::new(File::open(&clean).unwrap()))
+            .unwrap()
+            .board;
+        board.cells_mut()[1].shape.points.reverse();
+        std::fs::write(&clean, BoardFile::new(board).to_json().unwrap()).unwrap();
+        let (code, out) = run_cli(&["validate", &clean]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(out.starts_with("warning: cell 1:"), "{out}");
+        assert_eq!(
+            run_cli(&["validate", "--strict", &clean]).0,
+            ExitCode::FAILURE
+        );
+        std::fs::remove_file(&clean).unwrap();
+    }
+
+    #[test]
+    fn test_run_stats() {
+        let input = board_file("stats.json", false);
+        let (code, out) = run_cli(&["stats", &input]);
+        std::fs::remove_file(&input).unwrap();
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(
+            out.lines().take(4).collect_vec(),
+            [
+                "cells: 2",
+                "links: 2 (0 one-way)",
+                "neighbors per cell: 1 to 1",
+                "capacity: 0",
+            ]
+        );
+        assert!(out.contains("meshes: 0 (0 lines, 0 triangles)"), "{out}");
+    }
+
+    #[test]
+    fn test_run_convert() {
+        let input = board_file("convert.json", false);
+        let (svg, json, png) = (temp("out.svg"), temp("out.json"), temp("out.png"));
+        assert_eq!(run_cli(&["convert", &input, &svg]).0, ExitCode::SUCCESS);
+        assert_eq!(run_cli(&["convert", &input, &json]).0, ExitCode::SUCCESS);
+        assert_eq!(run_cli(&["convert", &input, &png]).0, ExitCode::from(2));
+
+        let svg_contents = std::fs::read_to_string(&svg).unwrap();
+        assert!(svg_contents.starts_with("<svg"));
+        let converted = BoardFile::from_reader(BufReader::new(File::open(&json).unwrap()));
+        assert_eq!(converted.unwrap().board.cells().len(), 2);
+        assert!(!std::path::Path::new(&png).exists());
+        for x in [input, svg, json] {
+            std::fs::remove_file(x).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_run_import() {
+        // A unit square drawn as a line loop, and a file with no meshes at all
+        let square = r#"{
+            "asset": {"version": "2.0"},
+            "buffers": [{
+                "byteLength": 48,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAACAPwAAgD8AAAAAAAAAAAAAgD8AAAAA"
+            }],
+            "bufferViews": [{"buffer": 0, "byteLength": 48}],
+            "accessors": [{
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": 4,
+                "type": "VEC3",
+                "min": [0, 0, 0],
+                "max": [1, 1, 0]
+            }],
+            "meshes": [{"name": "Square", "primitives": [{"attributes": {"POSITION": 0}, "mode": 2}]}]
+        }"#;
+        let (input, empty) = (temp("square.gltf"), temp("empty.gltf"));
+        std::fs::write(&input, square).unwrap();
+        std::fs::write(&empty, r#"{"asset": {"version": "2.0"}}"#).unwrap();
+
+        let (code, out) = run_cli(&["import", &input, "--board", "Square"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        let board = BoardFile::from_slice(out.as_bytes()).unwrap().board;
+        assert_eq!(board.cells().len(), 1);
+        assert_eq!(
+            run_cli(&["import", &input, "--board", "Circle"]).0,
+            ExitCode::from(2)
+        );
+        assert!(matches!(
+            import(std::path::Path::new(&empty), None),
+            Err(Failure::Error(Error::GltfStructure(_)))
+        ));
+        for x in [input, empty] {
+            std::fs::remove_file(x).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_arguments() {
+        let mut args = ["a.json", "--width", "10", "--neighbors"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(
+            take_option(&mut args, &["--width"])
+                .ok()
+                .flatten()
+                .as_deref(),
+            Some("10")
+        );
+        assert!(take_flag(&mut args, "--neighbors"));
+        assert!(positional::<1>(args.clone()).is_ok());
+        assert!(positional::<2>(args).is_err());
+    }
+}
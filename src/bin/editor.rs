@@ -1,9 +1,10 @@
-use std::{fs::File, iter::once};
+use std::{collections::HashMap, fs::File};
 
 use bevy::{
     prelude::*,
     render::camera::ScalingMode,
     tasks::{AsyncComputeTaskPool, Task},
+    utils::FloatOrd,
     window::PrimaryWindow,
     winit::WinitWindows,
 };
@@ -13,21 +14,39 @@ use bevy_egui::{
 };
 use bevy_mod_async::prelude::*;
 use futures_lite::future::{block_on, poll_once};
-use gltf::Gltf;
 use grid_builder::{
-    board::{Board, BoardColor, BoardMesh, Cell, Mesh, Path},
+    analysis::{analysis_plugin, settled, BoardAnalysis},
+    board::{Board, BoardColor, BoardMesh, Cell, Keyframe, Mesh, Path},
+    custom_gizmos::CustomGizmos,
+    error::Result,
     export::ExportBoardCmd,
-    import::process_gltf,
-    nav::{nav_plugin, Pick},
+    format::BoardFile,
+    history::{shortcut, Edit, History, Shortcut},
+    import::{import_file, Import},
+    nav::{nav_plugin, Cursor, Pick},
+    notify::{notify_plugin, Notifications},
+    path::Interpolation,
+    validate::{validate, Diagnostic, Severity, Subject},
 };
+use itertools::Itertools;
 
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins, EguiPlugin, AsyncTasksPlugin, nav_plugin))
+        .add_plugins((
+            DefaultPlugins,
+            EguiPlugin,
+            AsyncTasksPlugin,
+            nav_plugin,
+            notify_plugin,
+            analysis_plugin,
+        ))
         .insert_resource(ClearColor(Color::BLACK))
         .init_resource::<DrawToggles>()
         .init_resource::<Board>()
         .init_resource::<ImportedMeshes>()
+        .init_resource::<PathEdit>()
+        .init_resource::<History<BoardEdit>>()
+        .init_resource::<Focus>()
         .add_systems(Startup, setup)
         .add_systems(
             Update,
@@ -35,8 +54,11 @@ fn main() {
                 toolbar,
                 meshes_panel,
                 draw_toggle_window,
-                (draw_board, board_panel).run_if(resource_exists::<Board>),
-                handle_picks,
+                (draw_board, board_panel, issues_panel, update_analysis)
+                    .run_if(resource_exists::<Board>),
+                handle_picks.before(edit_paths),
+                edit_paths,
+                (history_panel, undo_redo),
             ),
         )
         .run();
@@ -56,7 +78,7 @@ fn setup(mut commands: Commands) {
 }
 
 #[derive(Resource)]
-struct LoadBoardTask(Task<Option<Board>>);
+struct LoadBoardTask(Task<Option<Result<Board>>>);
 
 fn toolbar(
     ui: EguiContexts,
@@ -64,15 +86,21 @@ fn toolbar(
     windows: NonSend<WinitWindows>,
     load_task: Option<ResMut<LoadBoardTask>>,
     board: Option<Res<Board>>,
+    (mut history, mut notifications): (ResMut<History<BoardEdit>>, ResMut<Notifications>),
     mut commands: Commands,
 ) {
     egui::Window::new("Board Editor").show(ui.ctx(), |ui| {
         if let Some(mut load_task) = load_task {
             ui.spinner();
             match block_on(poll_once(&mut load_task.0)) {
-                Some(Some(board)) => {
+                Some(Some(Ok(board))) => {
                     commands.remove_resource::<LoadBoardTask>();
                     commands.insert_resource(board);
+                    history.clear();
+                }
+                Some(Some(Err(e))) => {
+                    commands.remove_resource::<LoadBoardTask>();
+                    notifications.push(format!("Error loading board: {e}"));
                 }
                 Some(None) => commands.remove_resource::<LoadBoardTask>(),
                 None => {}
@@ -86,11 +114,12 @@ fn toolbar(
                     .set_parent(parent)
                     .set_title("Open Board");
                 let task = task_pool.spawn(async {
-                    let Some(path) = dialog.pick_file().await else {
-                        return None;
+                    let path = dialog.pick_file().await?;
+                    let file = match File::open(path.path()) {
+                        Ok(file) => file,
+                        Err(e) => return Some(Err(e.into())),
                     };
-                    let file = File::open(path.path()).unwrap();
-                    Some(serde_json::from_reader(file).unwrap())
+                    Some(BoardFile::from_reader(file).map(|x| x.board))
                 });
                 commands.insert_resource(LoadBoardTask(task));
             }
@@ -104,39 +133,68 @@ fn toolbar(
 }
 
 #[derive(Resource, Default)]
-struct ImportedMeshes(Vec<Vec<Cell>>, Vec<Mesh>);
+struct ImportedMeshes(Import);
 
 fn meshes_panel(
     mut ui: EguiContexts,
     mut board: ResMut<Board>,
+    mut history: ResMut<History<BoardEdit>>,
     mut meshes: ResMut<ImportedMeshes>,
+    mut notifications: ResMut<Notifications>,
 ) {
     egui::Window::new("Imported").show(ui.ctx_mut(), |ui| {
         if ui.button("Import...").clicked() {
-            if let Some(path) = rfd::FileDialog::new().pick_file() {
-                if let Ok(file) = File::open(path) {
-                    if let Ok(model) = Gltf::from_reader(file) {
-                        let imported = process_gltf(model);
-                        meshes.0.extend(imported.0);
-                        meshes.1.extend(imported.1);
+            let dialog = rfd::FileDialog::new()
+                .add_filter("glTF Files", &["gltf", "glb"])
+                .set_title("Import glTF");
+            if let Some(path) = dialog.pick_file() {
+                match import_file(&path) {
+                    Ok(imported) => {
+                        for diagnostic in &imported.diagnostics {
+                            notifications.push(format!("{}: {diagnostic}", path.display()));
+                        }
+                        meshes.0.boards.extend(imported.boards);
+                        meshes.0.meshes.extend(imported.meshes);
+                    }
+                    Err(e) => {
+                        notifications.push(format!("Error importing {}: {e}", path.display()))
                     }
                 }
             }
         }
         ui.label("Boards");
-        for cells in &meshes.0 {
-            if ui.button("Load").clicked() {
-                board.cells = cells.clone();
-            }
+        for imported in &meshes.0.boards {
+            ui.horizontal(|ui| {
+                ui.label(&imported.name);
+                if !imported.diagnostics.is_empty() {
+                    let details = imported.diagnostics.iter().join("\n");
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("⚠ {}", imported.diagnostics.len()),
+                    )
+                    .on_hover_text(details);
+                }
+                if ui.button("Load").clicked() {
+                    let edit = BoardEdit::SetCells {
+                        before: board.cells().to_vec(),
+                        after: imported.cells.clone(),
+                    };
+                    history.push(&mut board, edit);
+                }
+            });
         }
         ui.label("Meshes");
-        for mesh in &meshes.1 {
-            if ui.button("Add").clicked() {
-                board.meshes.push(BoardMesh {
-                    color: BoardColor::PlayerColor,
-                    mesh: mesh.clone(),
-                })
-            }
+        for imported in &meshes.0.meshes {
+            ui.horizontal(|ui| {
+                ui.label(&imported.name);
+                if ui.button("Add").clicked() {
+                    let mesh = BoardMesh {
+                        color: BoardColor::PlayerColor,
+                        mesh: imported.mesh.clone(),
+                    };
+                    history.push(&mut board, BoardEdit::AddMesh(mesh));
+                }
+            });
         }
     });
 }
@@ -165,6 +223,28 @@ fn vec3_ui(v: &mut Vec3, ui: &mut Ui) {
     });
 }
 
+fn interpolation_ui(id: usize, interpolation: &mut Interpolation, ui: &mut Ui) {
+    let name = |x: &Interpolation| match x {
+        Interpolation::Linear => "Linear",
+        Interpolation::CatmullRom => "Catmull-Rom",
+        Interpolation::CubicBezier(_) => "Bézier",
+    };
+    egui::ComboBox::from_id_source(("interpolation", id))
+        .selected_text(name(interpolation))
+        .show_ui(ui, |ui| {
+            for x in [
+                Interpolation::Linear,
+                Interpolation::CatmullRom,
+                Interpolation::CubicBezier(Default::default()),
+            ] {
+                let selected = name(&x) == name(interpolation);
+                if ui.selectable_label(selected, name(&x)).clicked() && !selected {
+                    *interpolation = x;
+                }
+            }
+        });
+}
+
 fn cell_ui(index: usize, cell: &mut Cell, ui: &mut Ui) -> Option<BoardResponse> {
     ui.collapsing("Neighbors", |ui| {
         for (&neighbor, path) in &mut cell.neighbors {
@@ -173,11 +253,33 @@ fn cell_ui(index: usize, cell: &mut Cell, ui: &mut Ui) -> Option<BoardResponse>
                 egui::CollapsingHeader::new("Path")
                     .id_source(neighbor)
                     .show(ui, |ui| {
-                        for (keyframe, point) in &mut path.0 {
+                        interpolation_ui(neighbor, &mut path.interpolation, ui);
+                        let mut retimed = None;
+                        for (keyframe, point) in &mut path.keyframes {
                             ui.horizontal(|ui| {
-                                ui.label(keyframe.0.to_string());
+                                let mut t = keyframe.0;
+                                let time = egui::DragValue::new(&mut t).speed(0.01).prefix("t ");
+                                if ui.add(time).changed() {
+                                    retimed = Some((*keyframe, Keyframe(t)));
+                                }
                                 vec2_ui(point, ui);
                             });
+                            if let Interpolation::CubicBezier(handles) = &mut path.interpolation {
+                                let old = handles.get(keyframe).copied().unwrap_or_default();
+                                let mut new = old;
+                                ui.horizontal(|ui| {
+                                    ui.label("In");
+                                    vec2_ui(&mut new.incoming, ui);
+                                    ui.label("Out");
+                                    vec2_ui(&mut new.outgoing, ui);
+                                });
+                                if new != old {
+                                    handles.insert(*keyframe, new);
+                                }
+                            }
+                        }
+                        if let Some((from, to)) = retimed {
+                            path.retime(from, to);
                         }
                     });
             });
@@ -185,6 +287,13 @@ fn cell_ui(index: usize, cell: &mut Cell, ui: &mut Ui) -> Option<BoardResponse>
     });
     ui.collapsing("Shape", |ui| {
         cell.shape.points.iter_mut().for_each(|x| vec2_ui(x, ui));
+        for (i, hole) in cell.shape.holes.iter_mut().enumerate() {
+            egui::CollapsingHeader::new(format!("Hole {i}"))
+                .id_source(("hole", i))
+                .show(ui, |ui| {
+                    hole.iter_mut().for_each(|x| vec2_ui(x, ui));
+                });
+        }
     });
     ui.label("Position");
     vec2_ui(&mut cell.position, ui);
@@ -260,42 +369,57 @@ fn board_mesh_ui(mesh: &mut Mesh, ui: &mut Ui) {
     }
 }
 
-fn board_panel(ui: EguiContexts, mut board: ResMut<Board>) {
+fn board_panel(
+    ui: EguiContexts,
+    mut board: ResMut<Board>,
+    mut history: ResMut<History<BoardEdit>>,
+    focus: Res<Focus>,
+) {
+    // Expand and scroll to whatever was just picked in the issues panel
+    let reveal = |subject: Subject, header: egui::CollapsingHeader| {
+        if focus.is_changed() && focus.0 == Some(subject) {
+            header.open(Some(true))
+        } else {
+            header
+        }
+    };
+    let scroll = |subject: Subject, response: &egui::Response| {
+        if focus.is_changed() && focus.0 == Some(subject) {
+            response.scroll_to_me(Some(egui::Align::Center));
+        }
+    };
+    // The UI edits copies, which are then applied through the history so they can be undone
+    let mut edit = None;
     egui::Window::new("Board").show(ui.ctx(), |ui| {
         ui.heading("Cells");
         egui::ScrollArea::vertical()
             .id_source("cells")
             .max_height(200.0)
             .show(ui, |ui| {
-                let mut response = None;
-                for (i, cell) in board.cells.iter_mut().enumerate() {
-                    egui::CollapsingHeader::new(i.to_string())
-                        .id_source(format!("cell{i}"))
-                        .show(ui, |ui| {
-                            response = cell_ui(i, cell, ui);
-                        });
-                }
-                match response {
-                    Some(BoardResponse::Remove(x)) => {
-                        board.cells.remove(x);
-                        for cell in &mut board.cells {
-                            cell.neighbors = cell
-                                .neighbors
-                                .drain()
-                                .filter_map(|(n, path)| {
-                                    if n > x {
-                                        Some((n - 1, path))
-                                    } else if n < x {
-                                        Some((n, path))
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .collect();
+                for (i, cell) in board.cells().iter().enumerate() {
+                    let header =
+                        egui::CollapsingHeader::new(i.to_string()).id_source(format!("cell{i}"));
+                    let response = reveal(Subject::Cell(i), header).show(ui, |ui| {
+                        let mut after = cell.clone();
+                        match cell_ui(i, &mut after, ui) {
+                            Some(BoardResponse::Remove(x)) => {
+                                edit = Some(BoardEdit::RemoveCell {
+                                    index: x,
+                                    removed: None,
+                                });
+                            }
+                            None if after != *cell => {
+                                edit = Some(BoardEdit::SetCell {
+                                    index: i,
+                                    before: cell.clone(),
+                                    after,
+                                });
+                            }
+                            None => {}
                         }
-                    }
-                    None => {}
-                };
+                    });
+                    scroll(Subject::Cell(i), &response.header_response);
+                }
             });
         ui.separator();
         ui.heading("Meshes");
@@ -303,22 +427,84 @@ fn board_panel(ui: EguiContexts, mut board: ResMut<Board>) {
             .id_source("meshes")
             .max_height(200.0)
             .show(ui, |ui| {
-                let mut response = None;
-                for (i, mesh) in board.meshes.iter_mut().enumerate() {
-                    egui::CollapsingHeader::new(i.to_string())
-                        .id_source(format!("mesh{i}"))
-                        .show(ui, |ui| {
-                            if ui.button("🗑").clicked() {
-                                response = Some(BoardResponse::Remove(i));
-                            }
-                            board_color_ui(&mut mesh.color, ui);
-                            board_mesh_ui(&mut mesh.mesh, ui);
-                        });
+                for (i, mesh) in board.meshes.iter().enumerate() {
+                    let header =
+                        egui::CollapsingHeader::new(i.to_string()).id_source(format!("mesh{i}"));
+                    let response = reveal(Subject::Mesh(i), header).show(ui, |ui| {
+                        if ui.button("🗑").clicked() {
+                            edit = Some(BoardEdit::RemoveMesh {
+                                index: i,
+                                removed: None,
+                            });
+                        }
+                        let mut after = mesh.clone();
+                        board_color_ui(&mut after.color, ui);
+                        board_mesh_ui(&mut after.mesh, ui);
+                        if after != *mesh && edit.is_none() {
+                            edit = Some(BoardEdit::SetMesh {
+                                index: i,
+                                before: mesh.clone(),
+                                after,
+                            });
+                        }
+                    });
+                    scroll(Subject::Mesh(i), &response.header_response);
                 }
-                if let Some(response) = response {
-                    match response {
-                        BoardResponse::Remove(i) => {
-                            board.meshes.remove(i);
+            });
+    });
+    if let Some(edit) = edit {
+        history.push(&mut board, edit);
+    }
+}
+
+/// The cell or mesh last picked in the issues panel.
+#[derive(Resource, Default)]
+struct Focus(Option<Subject>);
+
+/// Lists the board's issues. Like the analysis, the board is only linted again once a change is
+/// finished, since the overlap check is too slow to run on every frame of a drag.
+fn issues_panel(
+    ui: EguiContexts,
+    board: Res<Board>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut issues: Local<Vec<Diagnostic>>,
+    mut stale: Local<bool>,
+    mut focus: ResMut<Focus>,
+    mut camera: Query<&mut Transform, With<Camera>>,
+) {
+    if settled(&mut stale, board.is_changed(), &mouse) {
+        *issues = validate(&board);
+    }
+    egui::Window::new("Issues").show(ui.ctx(), |ui| {
+        if issues.is_empty() {
+            ui.label("No issues found");
+            return;
+        }
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                for issue in issues.iter() {
+                    let color = match issue.severity {
+                        Severity::Warning => egui::Color32::YELLOW,
+                        Severity::Error => egui::Color32::RED,
+                    };
+                    let text = egui::RichText::new(format!("{}: {}", issue.subject, issue.message))
+                        .color(color);
+                    let mut response = ui.selectable_label(focus.0 == Some(issue.subject), text);
+                    if let Some(fix) = &issue.fix {
+                        response = response.on_hover_text(fix.as_str());
+                    }
+                    if response.clicked() {
+                        focus.0 = Some(issue.subject);
+                        let position = match issue.subject {
+                            Subject::Cell(i) => board.cells().get(i).map(|x| x.position),
+                            Subject::Mesh(_) => None,
+                        };
+                        if let Some(position) = position.filter(|x| x.is_finite()) {
+                            for mut transform in &mut camera {
+                                transform.translation.x = position.x;
+                                transform.translation.y = position.y;
+                            }
                         }
                     }
                 }
@@ -326,21 +512,464 @@ fn board_panel(ui: EguiContexts, mut board: ResMut<Board>) {
     });
 }
 
-fn handle_picks(mut picks: EventReader<Pick>, mut board: ResMut<Board>) {
+/// Rebuilds the analysis once a change is finished, so dragging a keyframe or a value doesn't
+/// reanalyze the board every frame.
+fn update_analysis(
+    board: Res<Board>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut analysis: ResMut<BoardAnalysis>,
+    mut stale: Local<bool>,
+) {
+    if settled(&mut stale, board.is_changed(), &mouse) {
+        analysis.update(board.clone());
+    }
+}
+
+/// A change to the board that can be undone. Every mutation of the board goes through one of
+/// these.
+#[derive(Debug)]
+enum BoardEdit {
+    SetCell {
+        index: usize,
+        before: Cell,
+        after: Cell,
+    },
+    /// Removes a cell along with the edges leading to it. `removed` holds the cell and its
+    /// incoming edges while the edit is applied.
+    RemoveCell {
+        index: usize,
+        removed: Option<(Cell, Vec<(usize, Path)>)>,
+    },
+    /// Adds the edge if it's missing and removes it otherwise. `path` holds the removed path while
+    /// the edge is gone.
+    ToggleNeighbor {
+        from: usize,
+        to: usize,
+        path: Path,
+        added: bool,
+    },
+    SetCells {
+        before: Vec<Cell>,
+        after: Vec<Cell>,
+    },
+    AddMesh(BoardMesh),
+    RemoveMesh {
+        index: usize,
+        removed: Option<BoardMesh>,
+    },
+    SetMesh {
+        index: usize,
+        before: BoardMesh,
+        after: BoardMesh,
+    },
+}
+
+impl BoardEdit {
+    fn toggle_neighbor(board: &mut Board, from: usize, to: usize, path: &mut Path) -> bool {
+        let neighbors = &mut board.cells_mut()[from].neighbors;
+        match neighbors.remove(&to) {
+            Some(removed) => {
+                *path = removed;
+                false
+            }
+            None => {
+                neighbors.insert(to, path.clone());
+                true
+            }
+        }
+    }
+}
+
+impl Edit for BoardEdit {
+    type Target = Board;
+
+    fn apply(&mut self, board: &mut Board) {
+        match self {
+            BoardEdit::SetCell { index, after, .. } => board.cells_mut()[*index] = after.clone(),
+            BoardEdit::RemoveCell { index, removed } => {
+                let index = *index;
+                let cell = board.cells_mut().remove(index);
+                let mut incoming = Vec::new();
+                for (i, x) in board.cells_mut().iter_mut().enumerate() {
+                    incoming.extend(x.neighbors.remove(&index).map(|path| (i, path)));
+                    x.neighbors = x
+                        .neighbors
+                        .drain()
+                        .map(|(n, path)| (if n > index { n - 1 } else { n }, path))
+                        .collect();
+                }
+                *removed = Some((cell, incoming));
+            }
+            BoardEdit::ToggleNeighbor {
+                from,
+                to,
+                path,
+                added,
+            } => *added = Self::toggle_neighbor(board, *from, *to, path),
+            BoardEdit::SetCells { after, .. } => *board.cells_mut() = after.clone(),
+            BoardEdit::AddMesh(mesh) => board.meshes.push(mesh.clone()),
+            BoardEdit::RemoveMesh { index, removed } => {
+                *removed = Some(board.meshes.remove(*index));
+            }
+            BoardEdit::SetMesh { index, after, .. } => board.meshes[*index] = after.clone(),
+        }
+    }
+
+    fn undo(&mut self, board: &mut Board) {
+        match self {
+            BoardEdit::SetCell { index, before, .. } => board.cells_mut()[*index] = before.clone(),
+            BoardEdit::RemoveCell { index, removed } => {
+                let index = *index;
+                let Some((cell, incoming)) = removed.take() else {
+                    return;
+                };
+                for x in board.cells_mut() {
+                    x.neighbors = x
+                        .neighbors
+                        .drain()
+                        .map(|(n, path)| (if n >= index { n + 1 } else { n }, path))
+                        .collect();
+                }
+                board.cells_mut().insert(index, cell);
+                for (i, path) in incoming {
+                    let i = if i >= index { i + 1 } else { i };
+                    board.cells_mut()[i].neighbors.insert(index, path);
+                }
+            }
+            BoardEdit::ToggleNeighbor { from, to, path, .. } => {
+                Self::toggle_neighbor(board, *from, *to, path);
+            }
+            BoardEdit::SetCells { before, .. } => *board.cells_mut() = before.clone(),
+            BoardEdit::AddMesh(_) => {
+                board.meshes.pop();
+            }
+            BoardEdit::RemoveMesh { index, removed } => {
+                if let Some(mesh) = removed.take() {
+                    board.meshes.insert(*index, mesh);
+                }
+            }
+            BoardEdit::SetMesh { index, before, .. } => board.meshes[*index] = before.clone(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            BoardEdit::SetCell { index, .. } => format!("Edit cell {index}"),
+            BoardEdit::RemoveCell { index, .. } => format!("Remove cell {index}"),
+            BoardEdit::ToggleNeighbor {
+                from, to, added, ..
+            } => {
+                let verb = if *added { "Connect" } else { "Disconnect" };
+                format!("{verb} {from} → {to}")
+            }
+            BoardEdit::SetCells { after, .. } => format!("Load {} cells", after.len()),
+            BoardEdit::AddMesh(_) => "Add mesh".into(),
+            BoardEdit::RemoveMesh { index, .. } => format!("Remove mesh {index}"),
+            BoardEdit::SetMesh { index, .. } => format!("Edit mesh {index}"),
+        }
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        match (self, next) {
+            (
+                BoardEdit::SetCell { index, after, .. },
+                BoardEdit::SetCell {
+                    index: next_index,
+                    after: next_after,
+                    ..
+                },
+            ) if index == next_index => {
+                *after = next_after.clone();
+                true
+            }
+            (
+                BoardEdit::SetMesh { index, after, .. },
+                BoardEdit::SetMesh {
+                    index: next_index,
+                    after: next_after,
+                    ..
+                },
+            ) if index == next_index => {
+                *after = next_after.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn history_panel(
+    ui: EguiContexts,
+    mut history: ResMut<History<BoardEdit>>,
+    mut board: ResMut<Board>,
+) {
+    egui::Window::new("History").show(ui.ctx(), |ui| {
+        ui.horizontal(|ui| {
+            let undo = egui::Button::new("⟲ Undo");
+            if ui.add_enabled(!history.done().is_empty(), undo).clicked() {
+                history.undo(&mut board);
+            }
+            let redo = egui::Button::new("⟳ Redo");
+            if ui.add_enabled(!history.undone().is_empty(), redo).clicked() {
+                history.redo(&mut board);
+            }
+        });
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                // Clicking an entry undoes or redoes everything after it
+                let done = history.done().len();
+                let mut target = None;
+                if ui.selectable_label(done == 0, "Start").clicked() {
+                    target = Some(0);
+                }
+                for (i, edit) in history.done().iter().enumerate() {
+                    if ui
+                        .selectable_label(i + 1 == done, edit.describe())
+                        .clicked()
+                    {
+                        target = Some(i + 1);
+                    }
+                }
+                for (i, edit) in history.undone().iter().rev().enumerate() {
+                    let text = egui::RichText::new(edit.describe()).weak();
+                    if ui.selectable_label(false, text).clicked() {
+                        target = Some(done + i + 1);
+                    }
+                }
+                if let Some(target) = target {
+                    while history.done().len() > target && history.undo(&mut board) {}
+                    while history.done().len() < target && history.redo(&mut board) {}
+                }
+            });
+    });
+}
+
+fn undo_redo(
+    mut ui: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut history: ResMut<History<BoardEdit>>,
+    mut board: ResMut<Board>,
+) {
+    // Everything changed while the mouse is held, like a drag, is one undo step
+    if !mouse.pressed(MouseButton::Left) {
+        history.seal();
+    }
+    if ui.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    match shortcut(&keys) {
+        Some(Shortcut::Undo) => {
+            history.undo(&mut board);
+        }
+        Some(Shortcut::Redo) => {
+            history.redo(&mut board);
+        }
+        None => {}
+    }
+}
+
+/// How far outside a cell (in world units) a click still counts as picking it, so clicks landing
+/// exactly on grid lines go somewhere.
+const PICK_TOLERANCE: f32 = 0.05;
+
+fn handle_picks(
+    mut picks: EventReader<Pick>,
+    edit: Res<PathEdit>,
+    mut board: ResMut<Board>,
+    mut history: ResMut<History<BoardEdit>>,
+) {
+    // Clicks that grabbed a keyframe belong to the path editor
+    if edit.dragging {
+        picks.clear();
+        return;
+    }
     for &Pick { down, up } in picks.read() {
-        let (Some(down), Some(up)) = (board.pick(down), board.pick(up)) else {
+        let (Some(down), Some(up)) = (
+            board.pick_with_tolerance(down, PICK_TOLERANCE),
+            board.pick_with_tolerance(up, PICK_TOLERANCE),
+        ) else {
             continue;
         };
         if down == up {
             continue;
         }
-        let (start, end) = (board.cells[down].position, board.cells[up].position);
-        if board.cells[down].neighbors.remove(&up).is_none() {
-            board.cells[down]
-                .neighbors
-                .insert(up, Path::simple(start, end));
+        let (start, end) = (board.cells()[down].position, board.cells()[up].position);
+        let edit = BoardEdit::ToggleNeighbor {
+            from: down,
+            to: up,
+            path: Path::simple(start, end),
+            added: false,
+        };
+        history.push(&mut board, edit);
+    }
+}
+
+/// Radius (in world units) of the keyframe handles drawn on paths.
+const HANDLE_RADIUS: f32 = 0.1;
+/// Number of points each path is drawn with.
+const PATH_SAMPLES: usize = 32;
+
+/// A keyframe on the path from `cell` to `neighbor`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct KeyframeRef {
+    cell: usize,
+    neighbor: usize,
+    keyframe: Keyframe,
+}
+
+impl KeyframeRef {
+    fn path(self, board: &Board) -> Option<&Path> {
+        board.cells().get(self.cell)?.neighbors.get(&self.neighbor)
+    }
+
+    /// An edit changing this keyframe's path with `f`.
+    fn edit(self, board: &Board, f: impl FnOnce(&mut Path)) -> Option<BoardEdit> {
+        let before = board.cells().get(self.cell)?.clone();
+        let mut after = before.clone();
+        f(after.neighbors.get_mut(&self.neighbor)?);
+        Some(BoardEdit::SetCell {
+            index: self.cell,
+            before,
+            after,
+        })
+    }
+}
+
+#[derive(Resource, Default)]
+struct PathEdit {
+    selected: Option<KeyframeRef>,
+    /// Whether the selected keyframe follows the cursor until the mouse is released.
+    dragging: bool,
+}
+
+fn paths(board: &Board) -> impl Iterator<Item = (usize, usize, &Path)> {
+    board.cells().iter().enumerate().flat_map(|(cell, x)| {
+        x.neighbors
+            .iter()
+            .map(move |(&neighbor, path)| (cell, neighbor, path))
+    })
+}
+
+/// The keyframe handle under `pos`. A path's first and last keyframes sit on its cells, so they
+/// can't be grabbed; otherwise clicking a cell would pull its paths apart.
+fn nearest_keyframe(board: &Board, pos: Vec2) -> Option<KeyframeRef> {
+    paths(board)
+        .flat_map(|(cell, neighbor, path)| {
+            let inner = path.keyframes.len().saturating_sub(2);
+            path.keyframes
+                .iter()
+                .skip(1)
+                .take(inner)
+                .map(move |(&keyframe, &point)| {
+                    let at = KeyframeRef {
+                        cell,
+                        neighbor,
+                        keyframe,
+                    };
+                    (at, point.distance(pos))
+                })
+        })
+        .filter(|&(_, d)| d <= HANDLE_RADIUS)
+        .min_by_key(|&(_, d)| FloatOrd(d))
+        .map(|(at, _)| at)
+}
+
+/// The point closest to `pos` on any path, as a keyframe that could be inserted there. Times that
+/// already have a keyframe are skipped.
+fn nearest_path_point(board: &Board, pos: Vec2) -> Option<(KeyframeRef, Vec2)> {
+    paths(board)
+        .flat_map(|(cell, neighbor, path)| {
+            path.arc_length_table()
+                .into_iter()
+                .filter(|&(t, _)| !path.keyframes.contains_key(&Keyframe(t)))
+                .map(move |(t, _)| {
+                    let at = KeyframeRef {
+                        cell,
+                        neighbor,
+                        keyframe: Keyframe(t),
+                    };
+                    (at, path.sample(t))
+                })
+        })
+        .filter(|(_, x)| x.distance(pos) <= HANDLE_RADIUS)
+        .min_by_key(|(_, x)| FloatOrd(x.distance(pos)))
+}
+
+/// Drag keyframes to move them, Ctrl+click a path to add one, and Delete to remove the selected
+/// one.
+fn edit_paths(
+    mut ui: EguiContexts,
+    cursor: Res<Cursor>,
+    (mouse, keys): (Res<ButtonInput<MouseButton>>, Res<ButtonInput<KeyCode>>),
+    toggles: Res<DrawToggles>,
+    mut edit: ResMut<PathEdit>,
+    mut board: ResMut<Board>,
+    mut history: ResMut<History<BoardEdit>>,
+) {
+    if mouse.just_released(MouseButton::Left) || !toggles.paths {
+        edit.dragging = false;
+    }
+    if !toggles.paths {
+        return;
+    }
+    if let Some(at) = edit.selected {
+        let path = at.path(&board);
+        if !path.is_some_and(|x| x.keyframes.contains_key(&at.keyframe)) {
+            *edit = PathEdit::default();
+        }
+    }
+    let mut change = None;
+    if let Some(pos) = cursor.0 {
+        if mouse.just_pressed(MouseButton::Left) && !ui.ctx_mut().wants_pointer_input() {
+            let grabbed = if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+                nearest_path_point(&board, pos).and_then(|(at, _)| {
+                    let path = at.path(&board)?;
+                    let mut split = path.clone();
+                    if !split.split(at.keyframe) {
+                        return None;
+                    }
+                    change = at.edit(&board, |x| *x = split);
+                    Some(at)
+                })
+            } else {
+                nearest_keyframe(&board, pos)
+            };
+            edit.selected = grabbed;
+            edit.dragging = grabbed.is_some();
+        } else if edit.dragging {
+            if let Some(at) = edit.selected {
+                let moved = at
+                    .path(&board)
+                    .is_some_and(|x| x.keyframes.get(&at.keyframe) != Some(&pos));
+                if moved {
+                    change = at.edit(&board, |x| {
+                        x.keyframes.insert(at.keyframe, pos);
+                    });
+                }
+            }
+        }
+    }
+    if keys.any_just_pressed([KeyCode::Delete, KeyCode::Backspace])
+        && !ui.ctx_mut().wants_keyboard_input()
+    {
+        if let Some(at) = edit.selected.take() {
+            edit.dragging = false;
+            // Every path keeps at least its two ends
+            if at.path(&board).is_some_and(|x| x.keyframes.len() > 2) {
+                change = at.edit(&board, |x| {
+                    x.keyframes.remove(&at.keyframe);
+                    if let Interpolation::CubicBezier(handles) = &mut x.interpolation {
+                        handles.remove(&at.keyframe);
+                    }
+                });
+            }
         }
     }
+    if let Some(change) = change {
+        history.push(&mut board, change);
+    }
 }
 
 #[derive(Resource)]
@@ -349,11 +978,16 @@ struct DrawToggles {
     // Some(false): draw all edges
     // Some(true): draw one-way edges only
     edges: Option<bool>,
+    /// Draw paths as curves, with editable keyframes.
+    paths: bool,
 }
 
 impl Default for DrawToggles {
     fn default() -> Self {
-        Self { edges: Some(false) }
+        Self {
+            edges: Some(false),
+            paths: true,
+        }
     }
 }
 
@@ -371,27 +1005,45 @@ fn draw_toggle_window(mut ui: EguiContexts, mut toggles: ResMut<DrawToggles>) {
                 toggles.edges = Some(false);
             }
         }
+        ui.checkbox(&mut toggles.paths, "Draw paths");
     });
 }
 
-fn draw_board(board: Res<Board>, toggles: Res<DrawToggles>, mut gizmos: Gizmos) {
-    for (x, cell) in board.cells.iter().enumerate() {
-        let positions = cell
-            .shape
-            .points
+fn draw_board(
+    board: Res<Board>,
+    toggles: Res<DrawToggles>,
+    edit: Res<PathEdit>,
+    focus: Res<Focus>,
+    // Resampled paths by cell and neighbor, since resampling every path every frame adds up
+    mut polylines: Local<HashMap<(usize, usize), Vec<Vec2>>>,
+    mut gizmos: Gizmos,
+) {
+    if board.is_changed() {
+        *polylines = board
+            .cells()
             .iter()
-            .map(|&x| x)
-            .chain(once(cell.shape.points[0]));
-        gizmos.linestrip_2d(positions, Color::RED);
+            .enumerate()
+            .flat_map(|(x, cell)| cell.neighbors.iter().map(move |(&n, path)| ((x, n), path)))
+            .map(|(key, path)| (key, path.resample(PATH_SAMPLES)))
+            .collect();
+    }
+    for (x, cell) in board.cells().iter().enumerate() {
+        let color = if focus.0 == Some(Subject::Cell(x)) {
+            Color::WHITE
+        } else {
+            Color::RED
+        };
+        gizmos.polygon(&cell.shape, color);
         if let Some(only_one_way) = toggles.edges {
-            for &n in cell.neighbors.keys() {
+            // Links to missing cells show up in the issues panel instead
+            for other in cell.neighbors.keys().filter_map(|n| board.cells().get(*n)) {
                 let x_pos = cell.position;
-                let n_pos = board.cells[n].position;
+                let n_pos = other.position;
                 let dir = n_pos - x_pos;
                 let offset = dir.perp() * 0.15;
                 let x_pos = cell.position + offset;
-                let n_pos = board.cells[n].position + offset;
-                if !(only_one_way && board.cells[n].neighbors.contains_key(&x)) {
+                let n_pos = other.position + offset;
+                if !(only_one_way && other.neighbors.contains_key(&x)) {
                     gizmos
                         .arrow_2d(
                             x_pos.lerp(n_pos, 0.35),
@@ -402,5 +1054,25 @@ fn draw_board(board: Res<Board>, toggles: Res<DrawToggles>, mut gizmos: Gizmos)
                 }
             }
         }
+        if toggles.paths {
+            for (&n, path) in &cell.neighbors {
+                if let Some(points) = polylines.get(&(x, n)) {
+                    gizmos.linestrip_2d(points.iter().copied(), Color::YELLOW);
+                }
+                for (&keyframe, &point) in &path.keyframes {
+                    let at = KeyframeRef {
+                        cell: x,
+                        neighbor: n,
+                        keyframe,
+                    };
+                    let color = if edit.selected == Some(at) {
+                        Color::WHITE
+                    } else {
+                        Color::GOLD
+                    };
+                    gizmos.circle_2d(point, HANDLE_RADIUS, color);
+                }
+            }
+        }
     }
 }
@@ -2,24 +2,58 @@ use bevy::{prelude::*, render::camera::ScalingMode};
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use bevy_mod_async::prelude::*;
 use grid_builder::{
-    basic_grid::{hex, square, BaseCell, BaseCorner, Edge},
+    analysis::{analysis_plugin, settled, BoardAnalysis},
+    basic_grid::{
+        hex, square, triangle, BaseCell, BaseCorner, Edge, LatticeCell, Layout, Orientation,
+    },
     board::{self, Board, BoardColor, BoardMesh, Cell, Path},
     custom_gizmos::CustomGizmos,
+    error::Result,
     export::{ExportBoardCmd, Exporting},
-    nav::{nav_plugin, Pick},
+    format::BoardFile,
+    history::{shortcut, Edit, History, Shortcut},
+    nav::{nav_plugin, Cursor, Pick},
+    notify::{notify_plugin, Notifications},
+    planar::segments_intersect,
+    project::{Lattice, ProjectFile, ProjectGrid},
     util::MinMax,
 };
-use std::collections::{HashMap, HashSet};
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::BufReader,
+    mem::discriminant,
+};
 
 fn main() {
     App::new()
-        .add_plugins((DefaultPlugins, EguiPlugin, AsyncTasksPlugin, nav_plugin))
+        .add_plugins((
+            DefaultPlugins,
+            EguiPlugin,
+            AsyncTasksPlugin,
+            nav_plugin,
+            notify_plugin,
+            analysis_plugin,
+        ))
         .init_resource::<Grid>()
+        .init_resource::<History<GridEdit>>()
+        .init_resource::<Tools>()
+        .init_resource::<Symmetry>()
         .insert_resource(ClearColor(Color::BLACK))
         .add_systems(Startup, setup)
         .add_systems(
             Update,
-            (control_panel, count_capacity, handle_picks, draw_grid),
+            (
+                control_panel,
+                tools_panel,
+                confirm_grid_change.run_if(resource_exists::<PendingGrid>),
+                update_analysis,
+                handle_picks,
+                paint,
+                undo_redo,
+                draw_grid,
+            ),
         )
         .run();
 }
@@ -77,7 +111,16 @@ impl<C: BaseCell> std::hash::Hash for TriVert<C> {
     }
 }
 
-fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -> Board {
+/// `arrow_offset` is how far the tips of one-way arrows stick out from their edge, as a fraction of
+/// the edge's length. If `diagonals` is set, cells that share a corner are linked too, and each
+/// link gets a marker at the shared corner: a stroke along the move, or an arrow if it's one-way.
+fn build_board<C: BaseCell>(
+    cells: Vec<C>,
+    edges: Edges<C>,
+    layout: Layout,
+    arrow_offset: f32,
+    diagonals: bool,
+) -> Board {
     let mut boring_edges = HashSet::<Edge<C>>::default();
     let mut directed_edges = HashSet::<Edge<C>>::default();
 
@@ -86,7 +129,7 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
             // we're going to assume the edge is moving ccw around `cell`, so cw around `neighbor`
             let mut edge = cell.neighboring_edge(&neighbor);
             if cells.contains(&neighbor) {
-                match edges.edge_dir(&cell, &neighbor) {
+                match edges.edge_dir(cell, &neighbor) {
                     Some(EdgeDir::AToB) => {
                         edge.reverse();
                         directed_edges.insert(edge);
@@ -115,7 +158,7 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
         .collect::<Vec<_>>();
 
     // Then turn each boring edge into a pair of indices
-    let lines = boring_edges
+    let mut lines: Vec<[usize; 2]> = boring_edges
         .into_iter()
         .map(|[x, y]| {
             [
@@ -124,9 +167,9 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
             ]
         })
         .collect();
-    let line_vertices = corners
+    let mut line_vertices: Vec<Vec3> = corners
         .into_iter()
-        .map(|x| x.position().extend(0.0))
+        .map(|x| x.position(layout).extend(0.0))
         .collect();
 
     use TriVert::*;
@@ -139,7 +182,7 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
         .into_iter()
         .collect::<Vec<_>>();
 
-    let triangles = directed_edges
+    let mut triangles = directed_edges
         .into_iter()
         .map(|[x, y]| {
             [
@@ -150,13 +193,13 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
         })
         .collect::<Vec<_>>();
 
-    let triangle_vertices = corners
+    let mut triangle_vertices: Vec<Vec3> = corners
         .into_iter()
         .map(|x| {
             match x {
-                Corner(x) => x.position(),
+                Corner(x) => x.position(layout),
                 Tip(a, b) => {
-                    let (a, b) = (a.position(), b.position());
+                    let (a, b) = (a.position(layout), b.position(layout));
                     let midpoint = (a + b) / 2.0;
                     midpoint + (b - a).perp() * arrow_offset
                 }
@@ -165,6 +208,40 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
         })
         .collect();
 
+    let diagonal_links = cells
+        .iter()
+        .filter(|_| diagonals)
+        .flat_map(|a| a.diagonal_neighbors().into_iter().map(move |b| (*a, b)));
+    for (a, b) in diagonal_links {
+        if !cells.contains(&b) {
+            continue;
+        }
+        let one_way = match edges.edge_dir(&a, &b) {
+            Some(EdgeDir::AToB) => true,
+            None if a < b => false,
+            _ => continue,
+        };
+        let corner = a.neighboring_corner(&b).position(layout);
+        let along =
+            (b.position(layout) - a.position(layout)).normalize() * arrow_offset * layout.size;
+        if one_way {
+            let start = triangle_vertices.len();
+            triangle_vertices.extend(
+                [
+                    corner + along,
+                    corner - along + along.perp() * 0.5,
+                    corner - along - along.perp() * 0.5,
+                ]
+                .map(|x| x.extend(0.0)),
+            );
+            triangles.push([start, start + 1, start + 2]);
+        } else {
+            let start = line_vertices.len();
+            line_vertices.extend([corner - along, corner + along].map(|x| x.extend(0.0)));
+            lines.push([start, start + 1]);
+        }
+    }
+
     let mut meshes = vec![BoardMesh {
         color: BoardColor::PlayerColor,
         mesh: board::Mesh::IndexedLineMesh {
@@ -172,7 +249,7 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
             lines,
         },
     }];
-    if triangles.len() > 0 {
+    if !triangles.is_empty() {
         meshes.push(BoardMesh {
             color: BoardColor::PlayerColor,
             mesh: board::Mesh::IndexedTriMesh {
@@ -188,32 +265,121 @@ fn build_board<C: BaseCell>(cells: Vec<C>, edges: Edges<C>, arrow_offset: f32) -
 
     for cell in &old_cells {
         let neighbors = cell
-            .neighbors()
+            .linked_neighbors(diagonals)
             .into_iter()
             .filter(|x| edges.edge_dir(cell, x) != Some(EdgeDir::BToA))
             .filter_map(|n| old_cells.iter().position(|x| n == *x));
         let neighbors = neighbors
-            .map(|n| (n, Path::simple(cell.position(), old_cells[n].position())))
+            .map(|n| {
+                let path = Path::simple(cell.position(layout), old_cells[n].position(layout));
+                (n, path)
+            })
             .collect();
         cells.push(Cell {
             neighbors,
-            shape: cell.shape(),
-            position: cell.position(),
+            shape: cell.shape(layout),
+            position: cell.position(layout),
         });
     }
 
-    Board { cells, meshes }
+    Board::new(cells, meshes)
+}
+
+/// How far a board cell's position and corners may be from the lattice cell's for them to match, as
+/// a fraction of the cell size.
+const FIT_TOLERANCE: f32 = 0.01;
+
+fn fits<C: BaseCell>(cell: &C, board_cell: &Cell, layout: Layout) -> bool {
+    let shape = cell.shape(layout);
+    let points = &board_cell.shape.points;
+    let tolerance = FIT_TOLERANCE * layout.size;
+    board_cell.position.distance(cell.position(layout)) <= tolerance
+        && board_cell.shape.holes.is_empty()
+        && points.len() == shape.points.len()
+        && shape
+            .points
+            .iter()
+            .all(|a| points.iter().any(|b| a.distance(*b) <= tolerance))
+}
+
+/// Undoes [`build_board`] for the lattice `C`. Cells whose position and shape don't match a
+/// lattice cell are left out, and everything that couldn't be carried over is described in the
+/// returned list of problems.
+fn reconstruct<C: BaseCell>(
+    board: &Board,
+    layout: Layout,
+    diagonals: bool,
+) -> (HashSet<C>, Edges<C>, Vec<String>) {
+    let mut problems = Vec::new();
+    let mut index = HashMap::new();
+    let mut lattice = Vec::with_capacity(board.cells().len());
+    for (i, x) in board.cells().iter().enumerate() {
+        let cell = C::pick(x.position, layout);
+        if !fits(&cell, x, layout) {
+            problems.push(format!("Cell {i} doesn't fit the lattice"));
+            lattice.push(None);
+        } else if let Some(other) = index.insert(cell, i) {
+            index.insert(cell, other);
+            problems.push(format!("Cell {i} is a duplicate of cell {other}"));
+            lattice.push(None);
+        } else {
+            lattice.push(Some(cell));
+        }
+    }
+
+    let mut edges = Edges::default();
+    for (i, a) in lattice.iter().enumerate() {
+        let Some(a) = a else {
+            continue;
+        };
+        let neighbors = &board.cells()[i].neighbors;
+        for &n in neighbors.keys().sorted() {
+            match lattice.get(n) {
+                None => problems.push(format!("Edge {i} → {n} points to a missing cell")),
+                Some(Some(b)) if !a.linked_to(b, diagonals) => {
+                    problems.push(format!("Edge {i} → {n} joins cells that aren't adjacent"))
+                }
+                _ => {}
+            }
+        }
+        for b in a.linked_neighbors(diagonals) {
+            let Some(&j) = index.get(&b) else {
+                continue;
+            };
+            match (
+                neighbors.contains_key(&j),
+                board.cells()[j].neighbors.contains_key(&i),
+            ) {
+                (true, false) => edges.add_one_way_edge(*a, b),
+                (false, false) if i < j => {
+                    problems.push(format!("Cells {i} and {j} are adjacent but not connected"))
+                }
+                _ => {}
+            }
+        }
+    }
+    let cells = lattice.into_iter().flatten().collect();
+    (cells, edges, problems)
 }
 
-#[derive(Resource, Clone)]
+#[derive(Resource, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 enum Grid {
     BasicSquare {
         cells: HashSet<square::Cell>,
         edges: Edges<square::Cell>,
+        layout: Layout,
+        diagonals: bool,
     },
     BasicHex {
         cells: HashSet<hex::Cell>,
         edges: Edges<hex::Cell>,
+        layout: Layout,
+    },
+    BasicTriangle {
+        cells: HashSet<triangle::Cell>,
+        edges: Edges<triangle::Cell>,
+        layout: Layout,
     },
 }
 
@@ -228,6 +394,8 @@ impl Grid {
         Self::BasicSquare {
             cells: default(),
             edges: default(),
+            layout: square::Cell::DEFAULT_LAYOUT,
+            diagonals: false,
         }
     }
 
@@ -235,21 +403,271 @@ impl Grid {
         Self::BasicHex {
             cells: default(),
             edges: default(),
+            layout: hex::Cell::DEFAULT_LAYOUT,
         }
     }
-}
 
-impl Into<Board> for Grid {
-    fn into(self) -> Board {
+    fn default_triangle() -> Self {
+        Self::BasicTriangle {
+            cells: default(),
+            edges: default(),
+            layout: triangle::Cell::DEFAULT_LAYOUT,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Grid::BasicSquare { cells, .. } => cells.len(),
+            Grid::BasicHex { cells, .. } => cells.len(),
+            Grid::BasicTriangle { cells, .. } => cells.len(),
+        }
+    }
+
+    fn layout(&self) -> Layout {
+        match self {
+            Grid::BasicSquare { layout, .. } => *layout,
+            Grid::BasicHex { layout, .. } => *layout,
+            Grid::BasicTriangle { layout, .. } => *layout,
+        }
+    }
+
+    fn with_layout(&self, layout: Layout) -> Self {
+        let mut grid = self.clone();
+        match &mut grid {
+            Grid::BasicSquare { layout: x, .. } => *x = layout,
+            Grid::BasicHex { layout: x, .. } => *x = layout,
+            Grid::BasicTriangle { layout: x, .. } => *x = layout,
+        }
+        grid
+    }
+
+    fn diagonals(&self) -> bool {
+        matches!(
+            self,
+            Grid::BasicSquare {
+                diagonals: true,
+                ..
+            }
+        )
+    }
+
+    /// Turning diagonals off drops any one-way edges between diagonal neighbors.
+    fn with_diagonals(&self, diagonals: bool) -> Self {
+        let mut grid = self.clone();
+        if let Grid::BasicSquare {
+            edges,
+            diagonals: x,
+            ..
+        } = &mut grid
+        {
+            *x = diagonals;
+            edges.retain_linked(diagonals);
+        }
+        grid
+    }
+
+    fn to_project(&self) -> ProjectGrid {
         match self {
-            Grid::BasicSquare { cells, edges } => {
+            Grid::BasicSquare {
+                cells,
+                edges,
+                layout,
+                diagonals,
+            } => ProjectGrid::Square(edges.to_lattice(cells, *layout, *diagonals)),
+            Grid::BasicHex {
+                cells,
+                edges,
+                layout,
+            } => ProjectGrid::Hex(edges.to_lattice(cells, *layout, false)),
+            Grid::BasicTriangle {
+                cells,
+                edges,
+                layout,
+            } => ProjectGrid::Triangle(edges.to_lattice(cells, *layout, false)),
+        }
+    }
+
+    /// Rebuilds the grid a board was exported from, picking whichever lattice, layout and cell size
+    /// most of its cells fit, and then whichever has the fewest problems. Cell sizes are guessed
+    /// from the sides of a few cells spread over the board, so one odd cell can't throw off the
+    /// rest. Returns `None` if no cell fits any lattice.
+    fn from_board(board: &Board) -> Option<(Self, Vec<String>)> {
+        let cells = board.cells();
+        let step = (cells.len() / SIZE_SAMPLES).max(1);
+        // Shave off float error so sizes like 1.5 come back exactly
+        let sizes = cells
+            .iter()
+            .step_by(step)
+            .take(SIZE_SAMPLES)
+            .flat_map(|x| x.shape.points.iter().circular_tuple_windows())
+            .map(|(a, b)| (a.distance(*b) * 1e4).round())
+            .filter(|x| x.is_finite() && *x > 0.0)
+            .map(|x| x as i64)
+            .unique()
+            .map(|x| x as f32 / 1e4);
+        let square = |diagonals| {
+            move |cells, edges, layout| Grid::BasicSquare {
+                cells,
+                edges,
+                layout,
+                diagonals,
+            }
+        };
+        let candidates = |size| {
+            let flat = Layout::new(size);
+            let pointy = Layout {
+                orientation: Orientation::PointyTop,
+                ..flat
+            };
+            [
+                reconstruct_grid(board, flat, false, square(false)),
+                reconstruct_grid(board, flat, true, square(true)),
+                reconstruct_grid(board, flat, false, |cells, edges, layout| Grid::BasicHex {
+                    cells,
+                    edges,
+                    layout,
+                }),
+                reconstruct_grid(board, pointy, false, |cells, edges, layout| {
+                    Grid::BasicHex {
+                        cells,
+                        edges,
+                        layout,
+                    }
+                }),
+                reconstruct_grid(board, flat, false, |cells, edges, layout| {
+                    Grid::BasicTriangle {
+                        cells,
+                        edges,
+                        layout,
+                    }
+                }),
+            ]
+        };
+        // Ties go to the earlier candidate
+        sizes
+            .flat_map(candidates)
+            .filter(|x| x.0 > 0)
+            .min_by_key(|(count, _, problems)| (std::cmp::Reverse(*count), problems.len()))
+            .map(|(_, grid, problems)| (grid, problems))
+    }
+
+    fn from_project(project: ProjectGrid) -> Self {
+        match project {
+            ProjectGrid::Square(x) => {
+                let diagonals = x.diagonals;
+                let (cells, edges, layout) = Edges::from_lattice(x);
+                Grid::BasicSquare {
+                    cells,
+                    edges,
+                    layout,
+                    diagonals,
+                }
+            }
+            ProjectGrid::Hex(x) => {
+                let (cells, edges, layout) = Edges::from_lattice(x);
+                Grid::BasicHex {
+                    cells,
+                    edges,
+                    layout,
+                }
+            }
+            ProjectGrid::Triangle(x) => {
+                let (cells, edges, layout) = Edges::from_lattice(x);
+                Grid::BasicTriangle {
+                    cells,
+                    edges,
+                    layout,
+                }
+            }
+        }
+    }
+}
+
+/// Runs [`reconstruct`] and wraps the result up with `make`, along with how many cells fit.
+fn reconstruct_grid<C: BaseCell>(
+    board: &Board,
+    layout: Layout,
+    diagonals: bool,
+    make: impl FnOnce(HashSet<C>, Edges<C>, Layout) -> Grid,
+) -> (usize, Grid, Vec<String>) {
+    let (cells, edges, problems) = reconstruct(board, layout, diagonals);
+    (cells.len(), make(cells, edges, layout), problems)
+}
+
+/// How many cells [`Grid::from_board`] takes side lengths from when guessing the cell size.
+const SIZE_SAMPLES: usize = 8;
+
+/// A drag that changes the grid a little every frame.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Gesture {
+    Resize,
+    Paint,
+    Erase,
+}
+
+/// Snapshots of the grid before and after a change. Grids are small enough that this is simpler
+/// than describing each change.
+struct GridEdit {
+    description: String,
+    /// Set on each frame's edit during a drag, so the whole drag merges into one undo step.
+    gesture: Option<Gesture>,
+    before: Grid,
+    after: Grid,
+}
+
+impl Edit for GridEdit {
+    type Target = Grid;
+
+    fn apply(&mut self, grid: &mut Grid) {
+        *grid = self.after.clone();
+    }
+
+    fn undo(&mut self, grid: &mut Grid) {
+        *grid = self.before.clone();
+    }
+
+    fn describe(&self) -> String {
+        self.description.clone()
+    }
+
+    fn merge(&mut self, next: &Self) -> bool {
+        if self.gesture.is_none() || self.gesture != next.gesture {
+            return false;
+        }
+        self.after = next.after.clone();
+        true
+    }
+}
+
+impl From<Grid> for Board {
+    fn from(grid: Grid) -> Self {
+        match grid {
+            Grid::BasicSquare {
+                cells,
+                edges,
+                layout,
+                diagonals,
+            } => {
                 let mut cells = cells.iter().copied().collect::<Vec<_>>();
                 cells.sort_unstable();
-                build_board(cells, edges, 0.14)
+                build_board(cells, edges, layout, 0.14, diagonals)
             }
-            Grid::BasicHex { cells, edges } => {
+            Grid::BasicHex {
+                cells,
+                edges,
+                layout,
+            } => {
                 let cells = cells.iter().copied().collect::<Vec<_>>();
-                build_board(cells, edges, 0.21)
+                build_board(cells, edges, layout, 0.21, false)
+            }
+            Grid::BasicTriangle {
+                cells,
+                edges,
+                layout,
+            } => {
+                let mut cells = cells.iter().copied().collect::<Vec<_>>();
+                cells.sort_unstable();
+                build_board(cells, edges, layout, 0.1, false)
             }
         }
     }
@@ -261,7 +679,7 @@ enum EdgeDir {
     BToA,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 struct Edges<C: BaseCell>(HashMap<C, HashSet<C>>);
 
 impl<C: BaseCell> Default for Edges<C> {
@@ -285,6 +703,41 @@ impl<C: BaseCell> Edges<C> {
         }
     }
 
+    fn to_lattice(&self, cells: &HashSet<C>, layout: Layout, diagonals: bool) -> Lattice<C> {
+        let mut cells = cells.iter().copied().collect::<Vec<_>>();
+        cells.sort_unstable();
+        let mut one_way_edges = self
+            .into_iter()
+            .flat_map(|(&a, other)| other.iter().map(move |&b| [a, b]))
+            .collect::<Vec<_>>();
+        one_way_edges.sort_unstable();
+        Lattice {
+            cells,
+            one_way_edges,
+            layout: Some(layout),
+            diagonals,
+        }
+    }
+
+    /// Drops edges that don't join two adjacent cells of the lattice, so a hand-edited file can't
+    /// leave dangling edges behind.
+    fn from_lattice(lattice: Lattice<C>) -> (HashSet<C>, Self, Layout) {
+        let cells = lattice.cells.into_iter().collect::<HashSet<_>>();
+        let mut edges = Self::default();
+        for [a, b] in lattice.one_way_edges {
+            if cells.contains(&a) && cells.contains(&b) && a.linked_to(&b, lattice.diagonals) {
+                edges.add_one_way_edge(a, b);
+            }
+        }
+        (cells, edges, lattice.layout.unwrap_or(C::DEFAULT_LAYOUT))
+    }
+
+    fn retain_linked(&mut self, diagonals: bool) {
+        for (a, other) in &mut self.0 {
+            other.retain(|b| a.linked_to(b, diagonals));
+        }
+    }
+
     fn edge_dir(&self, a: &C, b: &C) -> Option<EdgeDir> {
         if self.0.get(a).is_some_and(|x| x.contains(b)) {
             Some(EdgeDir::AToB)
@@ -306,121 +759,899 @@ impl<'a, C: BaseCell> IntoIterator for &'a Edges<C> {
     }
 }
 
+fn tools_panel(
+    ui: EguiContexts,
+    grid: Res<Grid>,
+    mut tools: ResMut<Tools>,
+    mut symmetry: ResMut<Symmetry>,
+) {
+    egui::Window::new("Tools").show(ui.ctx(), |ui| {
+        ui.horizontal_wrapped(|ui| {
+            let region = match *grid {
+                Grid::BasicHex { .. } => "Hexagon",
+                _ => "Rectangle",
+            };
+            for (tool, name) in [
+                (Tool::Toggle, "Toggle"),
+                (Tool::Paint, "Paint"),
+                (Tool::Erase, "Erase"),
+                (Tool::Region, region),
+                (Tool::Flood, "Flood fill"),
+                (Tool::Range, "Range"),
+                (Tool::Center, "Center"),
+            ] {
+                ui.selectable_value(&mut tools.tool, tool, name);
+            }
+        });
+        if tools.tool == Tool::Range {
+            ui.horizontal(|ui| {
+                ui.label("Radius");
+                ui.add(egui::DragValue::new(&mut tools.radius).clamp_range(1..=MAX_RADIUS));
+            });
+        }
+        let (folds, axis_angle): (&[i32], _) = match *grid {
+            Grid::BasicSquare { .. } => (&[1, 2, 4], 45),
+            Grid::BasicHex { .. } => (&[1, 2, 3, 6], 30),
+            Grid::BasicTriangle { .. } => {
+                ui.label("Symmetry isn't available on triangle grids");
+                return;
+            }
+        };
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Rotation");
+            for &fold in folds {
+                let name = if fold == 1 {
+                    "None".into()
+                } else {
+                    format!("{fold}-fold")
+                };
+                ui.selectable_value(&mut symmetry.fold, fold, name);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut symmetry.mirror, "Mirror");
+            ui.add_enabled_ui(symmetry.mirror, |ui| {
+                egui::ComboBox::from_id_source("mirror axis")
+                    .selected_text(format!("{}°", symmetry.axis * axis_angle))
+                    .show_ui(ui, |ui| {
+                        for axis in 0..180 / axis_angle {
+                            let name = format!("{}°", axis * axis_angle);
+                            ui.selectable_value(&mut symmetry.axis, axis, name);
+                        }
+                    });
+            });
+        });
+        ui.label("Pick the center cell with the Center tool");
+    });
+}
+
+/// A grid type change waiting for the user to confirm that the current grid can be cleared.
+#[derive(Resource)]
+struct PendingGrid {
+    name: &'static str,
+    grid: Grid,
+}
+
 fn control_panel(
     mut ui: EguiContexts,
-    grid: Res<Grid>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History<GridEdit>>,
+    pending: Option<Res<PendingGrid>>,
     exporting: Option<Res<Exporting>>,
+    mut notifications: ResMut<Notifications>,
     mut commands: Commands,
 ) {
+    let mut change = None;
+    let mut gesture = None;
     egui::Window::new("Control Panel").show(ui.ctx_mut(), |ui| {
-        ui.add_enabled_ui(exporting.is_none(), |ui| {
+        ui.add_enabled_ui(exporting.is_none() && pending.is_none(), |ui| {
             if exporting.is_some() {
                 ui.spinner();
             }
             ui.horizontal(|ui| {
-                if ui
-                    .selectable_label(matches!(&*grid, Grid::BasicSquare { .. }), "Square")
-                    .clicked()
-                {
-                    if !matches!(grid.as_ref(), Grid::BasicSquare { .. }) {
-                        commands.insert_resource(Grid::default_square());
+                for (name, empty) in [
+                    ("Square", Grid::default_square()),
+                    ("Hexagon", Grid::default_hex()),
+                    ("Triangle", Grid::default_triangle()),
+                ] {
+                    let selected = discriminant(&*grid) == discriminant(&empty);
+                    if ui.selectable_label(selected, name).clicked() && !selected {
+                        if grid.len() == 0 {
+                            change = Some((format!("Switch to {name} grid"), empty));
+                        } else {
+                            commands.insert_resource(PendingGrid { name, grid: empty });
+                        }
+                    }
+                }
+            });
+            let mut layout = grid.layout();
+            ui.horizontal(|ui| {
+                ui.label("Cell size");
+                let size = egui::DragValue::new(&mut layout.size)
+                    .speed(0.01)
+                    .clamp_range(0.1..=100.0);
+                ui.add(size);
+            });
+            if matches!(*grid, Grid::BasicHex { .. }) {
+                ui.horizontal(|ui| {
+                    let orientation = &mut layout.orientation;
+                    ui.selectable_value(orientation, Orientation::FlatTop, "Flat top");
+                    ui.selectable_value(orientation, Orientation::PointyTop, "Pointy top");
+                });
+            }
+            if layout != grid.layout() {
+                if layout.size != grid.layout().size {
+                    gesture = Some(Gesture::Resize);
+                }
+                change = Some(("Change layout".into(), grid.with_layout(layout)));
+            }
+            if matches!(*grid, Grid::BasicSquare { .. }) {
+                let mut diagonals = grid.diagonals();
+                if ui.checkbox(&mut diagonals, "Diagonal neighbors").changed() {
+                    let description = if diagonals {
+                        "Link diagonal neighbors"
+                    } else {
+                        "Unlink diagonal neighbors"
+                    };
+                    change = Some((description.into(), grid.with_diagonals(diagonals)));
+                }
+            }
+            ui.horizontal(|ui| {
+                let undo = egui::Button::new("⟲ Undo");
+                let mut undo = ui.add_enabled(!history.done().is_empty(), undo);
+                if let Some(last) = history.done().last() {
+                    undo = undo.on_hover_text(last.describe());
+                }
+                if undo.clicked() {
+                    history.undo(&mut grid);
+                }
+                let redo = egui::Button::new("⟳ Redo");
+                let mut redo = ui.add_enabled(!history.undone().is_empty(), redo);
+                if let Some(next) = history.undone().last() {
+                    redo = redo.on_hover_text(next.describe());
+                }
+                if redo.clicked() {
+                    history.redo(&mut grid);
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Open Project...").clicked() {
+                    match open_project() {
+                        Ok(Some(opened)) => change = Some(("Open project".into(), opened)),
+                        Ok(None) => {}
+                        Err(e) => notifications.push(format!("Error opening project: {e}")),
                     }
                 }
-                if ui
-                    .selectable_label(matches!(grid.as_ref(), Grid::BasicHex { .. }), "Hexagon")
-                    .clicked()
-                {
-                    if !matches!(grid.as_ref(), Grid::BasicHex { .. }) {
-                        commands.insert_resource(Grid::default_hex());
+                if ui.button("Save Project...").clicked() {
+                    if let Err(e) = save_project(&grid) {
+                        notifications.push(format!("Error saving project: {e}"));
                     }
                 }
             });
+            if ui.button("Import Board...").clicked() {
+                match open_board().map(|x| x.map(|x| Grid::from_board(&x))) {
+                    Ok(Some(Some((imported, problems)))) => {
+                        if !problems.is_empty() {
+                            notifications.push(summarize_problems(&problems));
+                        }
+                        change = Some(("Import board".into(), imported));
+                    }
+                    Ok(Some(None)) => {
+                        notifications.push("Board doesn't match a square, hexagon or triangle grid")
+                    }
+                    Ok(None) => {}
+                    Err(e) => notifications.push(format!("Error importing board: {e}")),
+                }
+            }
             if ui.button("Export JSON...").clicked() {
                 commands.add(ExportBoardCmd(grid.clone().into()));
             }
         });
     });
+    if let Some((description, after)) = change {
+        let edit = GridEdit {
+            description,
+            gesture,
+            before: grid.clone(),
+            after,
+        };
+        history.push(&mut grid, edit);
+    }
 }
 
-fn handle_picks(mut picks: EventReader<Pick>, mut grid: ResMut<Grid>) {
-    for &Pick { down, up } in picks.read() {
-        match &mut *grid {
-            Grid::BasicSquare { cells, edges } => {
-                let (down, up) = (square::Cell::pick(down), square::Cell::pick(up));
-                if down == up {
-                    if !cells.remove(&down) {
-                        cells.insert(down);
-                    } else {
-                        edges.remove_cell(&down);
-                    }
-                } else if down.adjacent_to(&up) {
-                    if !(cells.contains(&up) && cells.contains(&down)) {
-                        continue;
-                    }
-                    edges.add_one_way_edge(down, up);
+fn confirm_grid_change(
+    ui: EguiContexts,
+    pending: Res<PendingGrid>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History<GridEdit>>,
+    mut commands: Commands,
+) {
+    egui::Window::new("Change grid type?")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+        .show(ui.ctx(), |ui| {
+            ui.label(format!(
+                "Switching to a {} grid clears all {} cells. This can be undone.",
+                pending.name.to_lowercase(),
+                grid.len()
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Clear and switch").clicked() {
+                    let edit = GridEdit {
+                        description: format!("Switch to {} grid", pending.name),
+                        gesture: None,
+                        before: grid.clone(),
+                        after: pending.grid.clone(),
+                    };
+                    history.push(&mut grid, edit);
+                    commands.remove_resource::<PendingGrid>();
                 }
-            }
-            Grid::BasicHex { cells, edges } => {
-                let (down, up) = (hex::Cell::pick(down), hex::Cell::pick(up));
-                if down == up {
-                    if !cells.remove(&down) {
-                        cells.insert(down);
-                    } else {
-                        edges.remove_cell(&down);
-                    }
-                } else if down.adjacent_to(&up) {
-                    if !(cells.contains(&up) && cells.contains(&down)) {
-                        continue;
-                    }
-                    edges.add_one_way_edge(down, up);
+                if ui.button("Cancel").clicked() {
+                    commands.remove_resource::<PendingGrid>();
                 }
-            }
-        };
+            });
+        });
+}
+
+const PROJECT_EXTENSION: &str = "grid";
+
+/// At most this many problems are listed when importing a board; the rest are only counted.
+const MAX_LISTED_PROBLEMS: usize = 10;
+
+fn summarize_problems(problems: &[String]) -> String {
+    let mut message = format!(
+        "Parts of the board didn't fit the grid:\n{}",
+        problems.iter().take(MAX_LISTED_PROBLEMS).join("\n")
+    );
+    if problems.len() > MAX_LISTED_PROBLEMS {
+        message += &format!("\n...and {} more", problems.len() - MAX_LISTED_PROBLEMS);
     }
+    message
 }
 
-fn draw_grid(grid: Res<Grid>, mut gizmos: Gizmos) {
-    match &*grid {
-        Grid::BasicSquare { cells, edges } => {
-            for x in cells {
-                gizmos.square(x.position(), Color::RED)
+fn open_board() -> Result<Option<Board>> {
+    let dialog = rfd::FileDialog::new()
+        .add_filter("JSON Files", &["json"])
+        .set_title("Import Board");
+    let Some(path) = dialog.pick_file() else {
+        return Ok(None);
+    };
+    let file = BoardFile::from_reader(BufReader::new(File::open(path)?))?;
+    Ok(Some(file.board))
+}
+
+fn open_project() -> Result<Option<Grid>> {
+    let dialog = rfd::FileDialog::new()
+        .add_filter("Grid Projects", &[PROJECT_EXTENSION])
+        .set_title("Open Project");
+    let Some(path) = dialog.pick_file() else {
+        return Ok(None);
+    };
+    let file = ProjectFile::from_reader(BufReader::new(File::open(path)?))?;
+    Ok(Some(Grid::from_project(file.grid)))
+}
+
+fn save_project(grid: &Grid) -> Result<()> {
+    let dialog = rfd::FileDialog::new()
+        .add_filter("Grid Projects", &[PROJECT_EXTENSION])
+        .set_title("Save Project");
+    let Some(path) = dialog.save_file() else {
+        return Ok(());
+    };
+    let json = ProjectFile::new(grid.to_project()).to_json()?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+enum Tool {
+    /// Click to add or remove a cell, or drag from one cell to a neighbor for a one-way edge.
+    #[default]
+    Toggle,
+    /// Drag to add every cell the cursor passes over.
+    Paint,
+    /// Drag to remove every cell the cursor passes over.
+    Erase,
+    /// Drag out a rectangle, or a hexagon on hex grids, and fill it.
+    Region,
+    /// Click an empty cell to fill the enclosed empty area around it.
+    Flood,
+    /// Click to fill every cell within [`Tools::radius`] steps.
+    Range,
+    /// Click to move the [`Symmetry`] center.
+    Center,
+}
+
+#[derive(Resource, Debug)]
+struct Tools {
+    tool: Tool,
+    radius: i32,
+}
+
+impl Default for Tools {
+    fn default() -> Self {
+        Self {
+            tool: default(),
+            radius: 2,
+        }
+    }
+}
+
+const MAX_RADIUS: i32 = 20;
+
+/// Lattice-specific shapes for the fill tools.
+trait Shapes: BaseCell {
+    /// Cells in the region dragged out from `a` to `b`.
+    fn region(a: Self, b: Self) -> Vec<Self>;
+    /// Cells within `radius` steps.
+    fn around(&self, radius: i32) -> Vec<Self>;
+    /// Cells on the way from `a` to `b`, both included, so paint strokes faster than a cell per
+    /// frame don't leave gaps.
+    fn stroke(a: Self, b: Self, layout: Layout) -> Vec<Self>;
+}
+
+impl Shapes for square::Cell {
+    fn region(a: Self, b: Self) -> Vec<Self> {
+        let ((x0, x1), (y0, y1)) = (a.x.min_max(b.x), a.y.min_max(b.y));
+        (x0..=x1)
+            .flat_map(|x| (y0..=y1).map(move |y| Self { x, y }))
+            .collect()
+    }
+
+    fn around(&self, radius: i32) -> Vec<Self> {
+        LatticeCell::range(self, radius)
+    }
+
+    fn stroke(a: Self, b: Self, _: Layout) -> Vec<Self> {
+        a.line(&b)
+    }
+}
+
+impl Shapes for hex::Cell {
+    /// A hexagon centered on `a` and reaching out to `b`.
+    fn region(a: Self, b: Self) -> Vec<Self> {
+        LatticeCell::range(&a, a.distance(&b))
+    }
+
+    fn around(&self, radius: i32) -> Vec<Self> {
+        LatticeCell::range(self, radius)
+    }
+
+    fn stroke(a: Self, b: Self, _: Layout) -> Vec<Self> {
+        a.line(&b)
+    }
+}
+
+impl Shapes for triangle::Cell {
+    fn region(a: Self, b: Self) -> Vec<Self> {
+        let ((x0, x1), (y0, y1)) = (a.x.min_max(b.x), a.y.min_max(b.y));
+        (x0..=x1)
+            .flat_map(|x| (y0..=y1).map(move |y| Self { x, y }))
+            .collect()
+    }
+
+    fn around(&self, radius: i32) -> Vec<Self> {
+        bfs(*self, radius as usize, |_| true)
+    }
+
+    /// Every cell the straight line between the centers of `a` and `b` touches. Touching counts,
+    /// so lines through corners still join up.
+    fn stroke(a: Self, b: Self, layout: Layout) -> Vec<Self> {
+        let (start, end) = (a.position(layout), b.position(layout));
+        bfs(a, usize::MAX, |x| {
+            let corners = x.corners().into_iter().map(|c| c.position(layout));
+            corners
+                .circular_tuple_windows()
+                .any(|(p, q)| segments_intersect(start, end, p, q))
+        })
+    }
+}
+
+/// Cells reachable from `start` in at most `max_steps` steps between neighbors, only stepping onto
+/// cells `open` accepts. `start` comes first, then the rest in order of distance.
+fn bfs<C: BaseCell>(start: C, max_steps: usize, open: impl Fn(&C) -> bool) -> Vec<C> {
+    let mut seen = HashSet::from([start]);
+    let mut frontier = vec![start];
+    let mut result = vec![start];
+    for _ in 0..max_steps {
+        frontier = frontier
+            .iter()
+            .flat_map(|x| x.neighbors())
+            .filter(|x| open(x) && seen.insert(*x))
+            .collect();
+        if frontier.is_empty() {
+            break;
+        }
+        result.extend(&frontier);
+    }
+    result
+}
+
+/// The empty cells connected to `start`, or `None` if they aren't walled in by `cells`. Anything
+/// that reaches past the bounding box of `cells` counts as getting out.
+fn flood<C: BaseCell>(cells: &HashSet<C>, layout: Layout, start: C) -> Option<Vec<C>> {
+    let bounds = cells
+        .iter()
+        .map(|x| x.position(layout))
+        .map(|x| Rect::from_corners(x, x))
+        .reduce(|a, b| a.union(b))?;
+    let inside = |x: &C| bounds.contains(x.position(layout));
+    if cells.contains(&start) || !inside(&start) {
+        return None;
+    }
+    let area = bfs(start, usize::MAX, |x| !cells.contains(x) && inside(x));
+    let leaks = area
+        .iter()
+        .flat_map(|x| x.neighbors())
+        .any(|x| !cells.contains(&x) && !inside(&x));
+    (!leaks).then_some(area)
+}
+
+/// Repeats every change to the grid at each of its images under a symmetry of the lattice. Only
+/// square and hex grids have any.
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+struct Symmetry {
+    /// Picked with the grid's layout to find the center cell.
+    center: Vec2,
+    /// Turns by a `1 / fold` turn about the center. Folds the lattice doesn't have count as 1.
+    fold: i32,
+    mirror: bool,
+    /// Mirror axis, as taken by [`LatticeCell::reflect`].
+    axis: i32,
+}
+
+impl Default for Symmetry {
+    fn default() -> Self {
+        Self {
+            center: Vec2::ZERO,
+            fold: 1,
+            mirror: false,
+            axis: 0,
+        }
+    }
+}
+
+/// Mirror axes and rotation guides reach this many cells out from the center.
+const GUIDE_LENGTH: f32 = 50.0;
+
+impl Symmetry {
+    fn fold<C: LatticeCell>(&self) -> i32 {
+        if self.fold > 0 && C::ROTATIONS % self.fold == 0 {
+            self.fold
+        } else {
+            1
+        }
+    }
+
+    fn is_identity<C: LatticeCell>(&self) -> bool {
+        self.fold::<C>() == 1 && !self.mirror
+    }
+
+    /// Images of `cell`, starting with itself. They aren't necessarily distinct.
+    fn images<C: LatticeCell>(&self, cell: C, center: C) -> Vec<C> {
+        let fold = self.fold::<C>();
+        let mirrored = if self.mirror {
+            vec![cell, cell.reflect(&center, self.axis)]
+        } else {
+            vec![cell]
+        };
+        mirrored
+            .into_iter()
+            .flat_map(|x| (0..fold).map(move |i| x.rotate(&center, i * C::ROTATIONS / fold)))
+            .collect()
+    }
+
+    /// Repeats the cells and one-way edges added or removed since `before` at their images.
+    fn repeat<C: LatticeCell>(
+        &self,
+        center: C,
+        diagonals: bool,
+        before: (&HashSet<C>, &Edges<C>),
+        cells: &mut HashSet<C>,
+        edges: &mut Edges<C>,
+    ) {
+        if self.is_identity::<C>() {
+            return;
+        }
+        let added = cells.difference(before.0).copied().collect::<Vec<_>>();
+        let removed = before.0.difference(cells).copied().collect::<Vec<_>>();
+        let new_edges = edges
+            .into_iter()
+            .flat_map(|(&a, other)| other.iter().map(move |&b| (a, b)))
+            .filter(|(a, b)| before.1.edge_dir(a, b) != Some(EdgeDir::AToB))
+            .collect::<Vec<_>>();
+        for x in added {
+            cells.extend(self.images(x, center));
+        }
+        for x in removed {
+            for image in self.images(x, center) {
+                cells.remove(&image);
+                edges.remove_cell(&image);
             }
-            for (a, other) in edges {
-                for b in other {
-                    let (start, end) = (cells.get(a).unwrap(), cells.get(b).unwrap());
-                    let (start, end) = (start.position(), end.position());
-                    let (start, end) = (start.lerp(end, 0.35), start.lerp(end, 0.65));
-                    gizmos
-                        .arrow_2d(start, end, Color::ORANGE)
-                        .with_tip_length(0.3);
+        }
+        for (a, b) in new_edges {
+            let images = self
+                .images(a, center)
+                .into_iter()
+                .zip(self.images(b, center));
+            for (a, b) in images {
+                if cells.contains(&a) && cells.contains(&b) && a.linked_to(&b, diagonals) {
+                    edges.add_one_way_edge(a, b);
                 }
             }
         }
-        Grid::BasicHex { cells, edges } => {
-            for x in cells {
-                gizmos.hex(x.position(), Color::RED);
+    }
+
+    /// Repeats the change from `before` to `after`, which must be the same kind of grid.
+    fn apply(&self, before: &Grid, after: &mut Grid) {
+        match (before, after) {
+            (
+                Grid::BasicSquare {
+                    cells: old_cells,
+                    edges: old_edges,
+                    ..
+                },
+                Grid::BasicSquare {
+                    cells,
+                    edges,
+                    layout,
+                    diagonals,
+                },
+            ) => {
+                let center = square::Cell::pick(self.center, *layout);
+                let before = (old_cells, old_edges);
+                self.repeat(center, *diagonals, before, cells, edges);
             }
-            for (a, other) in edges {
-                for b in other {
-                    let (start, end) = (cells.get(a).unwrap(), cells.get(b).unwrap());
-                    let (start, end) = (start.position(), end.position());
-                    let (start, end) = (start.lerp(end, 0.35), start.lerp(end, 0.65));
-                    gizmos
-                        .arrow_2d(start, end, Color::ORANGE)
-                        .with_tip_length(0.3);
-                }
+            (
+                Grid::BasicHex {
+                    cells: old_cells,
+                    edges: old_edges,
+                    ..
+                },
+                Grid::BasicHex {
+                    cells,
+                    edges,
+                    layout,
+                },
+            ) => {
+                let center = hex::Cell::pick(self.center, *layout);
+                let before = (old_cells, old_edges);
+                self.repeat(center, false, before, cells, edges);
+            }
+            _ => {}
+        }
+    }
+
+    /// Marks the center, each mirror axis, and spokes splitting the rotations.
+    fn draw<C: LatticeCell>(&self, layout: Layout, gizmos: &mut Gizmos) {
+        if self.is_identity::<C>() {
+            return;
+        }
+        let center = C::pick(self.center, layout);
+        let origin = center.position(layout);
+        // The lattice's horizontal, which pointy-top hexes turn away from the world's
+        let horizontal = center
+            .neighbors()
+            .into_iter()
+            .map(|x| x.position(layout) + x.mirror(&center).position(layout) - 2.0 * origin)
+            .find(|x| x.length() > 1e-3 * layout.size)
+            .map_or(0.0, |x| x.y.atan2(x.x));
+        let length = GUIDE_LENGTH * layout.size;
+        let direction = |angle: f32| Vec2::from_angle(horizontal + angle) * length;
+        gizmos.circle_2d(origin, 0.3 * layout.size, Color::CYAN);
+        let fold = self.fold::<C>();
+        let half_turn = std::f32::consts::PI / C::ROTATIONS as f32;
+        for i in 0..fold {
+            let turn = (i * C::ROTATIONS / fold) as f32 * 2.0 * half_turn;
+            if self.mirror {
+                let axis = direction(turn + self.axis as f32 * half_turn);
+                gizmos.line_2d(origin - axis, origin + axis, Color::CYAN);
+            } else if fold > 1 {
+                // Between mirror axes, where there'd be any
+                let spoke = direction(turn + half_turn / 2.0);
+                gizmos.line_2d(origin, origin + spoke, Color::GRAY);
             }
         }
+    }
+}
+
+/// Applies one of the fill tools to a click or drag from `down` to `up`, returning a description
+/// of the change if there was one.
+fn fill_cells<C: Shapes>(
+    cells: &mut HashSet<C>,
+    layout: Layout,
+    tools: &Tools,
+    down: Vec2,
+    up: Vec2,
+) -> std::result::Result<Option<String>, &'static str> {
+    let (down, up) = (C::pick(down, layout), C::pick(up, layout));
+    let (description, filled) = match tools.tool {
+        Tool::Region => ("Fill region", C::region(down, up)),
+        Tool::Flood => {
+            let area = flood(cells, layout, up).ok_or("Flood fill needs an enclosed empty area")?;
+            ("Flood fill", area)
+        }
+        Tool::Range => ("Fill range", up.around(tools.radius)),
+        Tool::Toggle | Tool::Paint | Tool::Erase | Tool::Center => return Ok(None),
     };
+    let before = cells.len();
+    cells.extend(filled);
+    Ok((cells.len() > before).then(|| description.into()))
 }
 
-fn count_capacity(ui: EguiContexts, grid: Res<Grid>) {
-    egui::Window::new("Capacity").show(ui.ctx(), |ui| {
-        let board: Board = grid.clone().into();
-        let cells = board.cells.iter();
-        let capacity: usize = cells.map(|x| x.neighbors.len().max(1) - 1).sum();
-        ui.label(format!("Capacity: {capacity}"));
-    });
+/// Applies a click or drag from `down` to `up` to the grid, returning a description of the change
+/// if there was one.
+fn pick_cells<C: BaseCell>(
+    cells: &mut HashSet<C>,
+    edges: &mut Edges<C>,
+    layout: Layout,
+    diagonals: bool,
+    down: Vec2,
+    up: Vec2,
+) -> Option<String> {
+    let (down, up) = (C::pick(down, layout), C::pick(up, layout));
+    if down == up {
+        if !cells.remove(&down) {
+            cells.insert(down);
+            Some(format!("Add {down:?}"))
+        } else {
+            edges.remove_cell(&down);
+            Some(format!("Remove {down:?}"))
+        }
+    } else if down.linked_to(&up, diagonals) {
+        if !(cells.contains(&up) && cells.contains(&down)) {
+            return None;
+        }
+        edges.add_one_way_edge(down, up);
+        Some(format!("One-way edge {down:?} → {up:?}"))
+    } else {
+        None
+    }
+}
+
+fn handle_picks(
+    mut picks: EventReader<Pick>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History<GridEdit>>,
+    tools: Res<Tools>,
+    mut symmetry: ResMut<Symmetry>,
+    pending: Option<Res<PendingGrid>>,
+    mut notifications: ResMut<Notifications>,
+) {
+    if pending.is_some() || matches!(tools.tool, Tool::Paint | Tool::Erase) {
+        picks.clear();
+        return;
+    }
+    for &Pick { down, up } in picks.read() {
+        if tools.tool == Tool::Center {
+            symmetry.center = up;
+            continue;
+        }
+        let mut after = grid.clone();
+        let description = if tools.tool == Tool::Toggle {
+            match &mut after {
+                Grid::BasicSquare {
+                    cells,
+                    edges,
+                    layout,
+                    diagonals,
+                } => pick_cells(cells, edges, *layout, *diagonals, down, up),
+                Grid::BasicHex {
+                    cells,
+                    edges,
+                    layout,
+                } => pick_cells(cells, edges, *layout, false, down, up),
+                Grid::BasicTriangle {
+                    cells,
+                    edges,
+                    layout,
+                } => pick_cells(cells, edges, *layout, false, down, up),
+            }
+        } else {
+            let filled = match &mut after {
+                Grid::BasicSquare { cells, layout, .. } => {
+                    fill_cells(cells, *layout, &tools, down, up)
+                }
+                Grid::BasicHex { cells, layout, .. } => {
+                    fill_cells(cells, *layout, &tools, down, up)
+                }
+                Grid::BasicTriangle { cells, layout, .. } => {
+                    fill_cells(cells, *layout, &tools, down, up)
+                }
+            };
+            filled.unwrap_or_else(|e| {
+                notifications.push(e);
+                None
+            })
+        };
+        symmetry.apply(&grid, &mut after);
+        if let Some(description) = description.filter(|_| after != *grid) {
+            let edit = GridEdit {
+                description,
+                gesture: None,
+                before: grid.clone(),
+                after,
+            };
+            history.push(&mut grid, edit);
+        }
+    }
+}
+
+/// Adds or removes the cells the cursor passed over since the last frame, returning whether any
+/// changed.
+fn paint_cells<C: Shapes>(
+    cells: &mut HashSet<C>,
+    edges: &mut Edges<C>,
+    layout: Layout,
+    erase: bool,
+    last: Vec2,
+    pos: Vec2,
+) -> bool {
+    let mut changed = false;
+    for cell in C::stroke(C::pick(last, layout), C::pick(pos, layout), layout) {
+        changed |= if erase {
+            edges.remove_cell(&cell);
+            cells.remove(&cell)
+        } else {
+            cells.insert(cell)
+        };
+    }
+    changed
+}
+
+/// Drives the paint and erase tools. A whole stroke is one undo step, since [`undo_redo`] seals
+/// the history once the mouse is let go.
+fn paint(
+    (tools, symmetry): (Res<Tools>, Res<Symmetry>),
+    cursor: Res<Cursor>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History<GridEdit>>,
+    pending: Option<Res<PendingGrid>>,
+    // Where the cursor was on the last frame of the stroke in progress, if any
+    mut stroke: Local<Option<Vec2>>,
+) {
+    // Only strokes that start on the grid count, not drags that wander off the UI
+    if mouse.just_pressed(MouseButton::Left) {
+        *stroke = cursor.0;
+    } else if !mouse.pressed(MouseButton::Left) {
+        *stroke = None;
+    }
+    let erase = match tools.tool {
+        Tool::Paint => false,
+        Tool::Erase => true,
+        _ => return,
+    };
+    let (Some(pos), Some(last)) = (cursor.0, *stroke) else {
+        return;
+    };
+    if pending.is_some() {
+        return;
+    }
+    *stroke = Some(pos);
+    let mut after = grid.clone();
+    let changed = match &mut after {
+        Grid::BasicSquare {
+            cells,
+            edges,
+            layout,
+            ..
+        } => paint_cells(cells, edges, *layout, erase, last, pos),
+        Grid::BasicHex {
+            cells,
+            edges,
+            layout,
+        } => paint_cells(cells, edges, *layout, erase, last, pos),
+        Grid::BasicTriangle {
+            cells,
+            edges,
+            layout,
+        } => paint_cells(cells, edges, *layout, erase, last, pos),
+    };
+    if changed {
+        symmetry.apply(&grid, &mut after);
+        let (description, gesture) = if erase {
+            ("Erase cells", Gesture::Erase)
+        } else {
+            ("Paint cells", Gesture::Paint)
+        };
+        let edit = GridEdit {
+            description: description.into(),
+            gesture: Some(gesture),
+            before: grid.clone(),
+            after,
+        };
+        history.push(&mut grid, edit);
+    }
+}
+
+fn undo_redo(
+    mut ui: EguiContexts,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut grid: ResMut<Grid>,
+    mut history: ResMut<History<GridEdit>>,
+) {
+    // Dragging the cell size around is one undo step
+    if !mouse.pressed(MouseButton::Left) {
+        history.seal();
+    }
+    if ui.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    match shortcut(&keys) {
+        Some(Shortcut::Undo) => {
+            history.undo(&mut grid);
+        }
+        Some(Shortcut::Redo) => {
+            history.redo(&mut grid);
+        }
+        None => {}
+    }
+}
+
+fn draw_lattice<C: BaseCell>(
+    cells: &HashSet<C>,
+    edges: &Edges<C>,
+    layout: Layout,
+    gizmos: &mut Gizmos,
+) {
+    for x in cells {
+        gizmos.polygon(&x.shape(layout), Color::RED);
+    }
+    let tip_length = 0.3 * layout.size / C::DEFAULT_LAYOUT.size;
+    for (a, other) in edges {
+        for b in other {
+            let (start, end) = (cells.get(a).unwrap(), cells.get(b).unwrap());
+            let (start, end) = (start.position(layout), end.position(layout));
+            let (start, end) = (start.lerp(end, 0.35), start.lerp(end, 0.65));
+            gizmos
+                .arrow_2d(start, end, Color::ORANGE)
+                .with_tip_length(tip_length);
+        }
+    }
+}
+
+fn draw_grid(grid: Res<Grid>, symmetry: Res<Symmetry>, mut gizmos: Gizmos) {
+    match &*grid {
+        Grid::BasicSquare {
+            cells,
+            edges,
+            layout,
+            ..
+        } => {
+            draw_lattice(cells, edges, *layout, &mut gizmos);
+            symmetry.draw::<square::Cell>(*layout, &mut gizmos);
+        }
+        Grid::BasicHex {
+            cells,
+            edges,
+            layout,
+        } => {
+            draw_lattice(cells, edges, *layout, &mut gizmos);
+            symmetry.draw::<hex::Cell>(*layout, &mut gizmos);
+        }
+        Grid::BasicTriangle {
+            cells,
+            edges,
+            layout,
+        } => draw_lattice(cells, edges, *layout, &mut gizmos),
+    };
+}
+
+/// Rebuilds the analysis once a change is finished. Building the board is slow for big grids, so
+/// drags that change the grid every frame (painting, resizing) only pay for it when let go.
+fn update_analysis(
+    grid: Res<Grid>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mut analysis: ResMut<BoardAnalysis>,
+    mut stale: Local<bool>,
+) {
+    if settled(&mut stale, grid.is_changed(), &mouse) {
+        analysis.update(grid.clone().into());
+    }
 }
 
 #[cfg(test)]
@@ -437,6 +1668,166 @@ mod test {
         ];
         let mut edges = Edges::<square::Cell>::default();
         edges.add_one_way_edge(cells[0], cells[1]);
-        build_board(cells, edges);
+        build_board(cells, edges, square::Cell::DEFAULT_LAYOUT, 0.14, false);
+    }
+
+    #[test]
+    fn test_diagonal_board() {
+        let cells = [
+            square::Cell { x: 0, y: 0 },
+            square::Cell { x: 1, y: 1 },
+            square::Cell { x: 2, y: 0 },
+        ];
+        let mut edges = Edges::default();
+        edges.add_one_way_edge(cells[1], cells[2]);
+        let grid = Grid::BasicSquare {
+            cells: cells.iter().copied().collect(),
+            edges,
+            layout: square::Cell::DEFAULT_LAYOUT,
+            diagonals: true,
+        };
+        let board: Board = grid.clone().into();
+        assert!(board.cells()[0].neighbors.contains_key(&1));
+        assert!(board.cells()[1].neighbors.contains_key(&0));
+        assert!(board.cells()[1].neighbors.contains_key(&2));
+        assert!(!board.cells()[2].neighbors.contains_key(&1));
+        // A stroke for the two-way link and an arrow for the one-way one
+        assert_eq!(board.meshes.len(), 2);
+        let (rebuilt, problems) = Grid::from_board(&board).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        assert!(rebuilt == grid);
+    }
+
+    #[test]
+    fn test_flood() {
+        // A ring of cells around the origin
+        let ring = square::Cell { x: 0, y: 0 }.ring(2);
+        let layout = square::Cell::DEFAULT_LAYOUT;
+        let mut cells = ring.iter().copied().collect::<HashSet<_>>();
+        let area = flood(&cells, layout, square::Cell { x: 0, y: 0 }).unwrap();
+        assert_eq!(area.len(), 5);
+        assert!(flood(&cells, layout, square::Cell { x: 2, y: 2 }).is_none());
+        // Break the ring and the fill leaks out
+        cells.remove(&square::Cell { x: 2, y: 0 });
+        assert!(flood(&cells, layout, square::Cell { x: 0, y: 0 }).is_none());
+    }
+
+    #[test]
+    fn test_triangle_stroke() {
+        let layout = triangle::Cell::DEFAULT_LAYOUT;
+        let cell = |x, y| triangle::Cell { x, y };
+        let along_row = triangle::Cell::stroke(cell(0, 0), cell(10, 0), layout);
+        assert_eq!(
+            along_row.into_iter().sorted().collect_vec(),
+            (0..=10).map(|x| cell(x, 0)).collect_vec()
+        );
+
+        let (a, b) = (cell(0, 0), cell(7, 3));
+        let stroke = triangle::Cell::stroke(a, b, layout);
+        assert_eq!(stroke[0], a);
+        assert!(stroke.contains(&b));
+        // Each cell joins on to one before it, so there are no gaps
+        for (i, x) in stroke.iter().enumerate().skip(1) {
+            assert!(stroke[..i].iter().any(|y| y.adjacent_to(x)));
+        }
+        assert_eq!(triangle::Cell::stroke(a, a, layout), [a]);
+    }
+
+    #[test]
+    fn test_symmetry() {
+        let symmetry = Symmetry {
+            center: Vec2::ZERO,
+            fold: 6,
+            mirror: true,
+            axis: 1,
+        };
+        let center = hex::Cell { q: 0, r: 0 };
+        let (a, b) = (hex::Cell { q: 2, r: 1 }, hex::Cell { q: 3, r: 1 });
+        let images = symmetry.images(a, center);
+        assert_eq!(images.iter().collect::<HashSet<_>>().len(), 12);
+
+        let before = Grid::default_hex();
+        let mut after = before.clone();
+        if let Grid::BasicHex { cells, edges, .. } = &mut after {
+            cells.extend([a, b]);
+            edges.add_one_way_edge(a, b);
+        }
+        symmetry.apply(&before, &mut after);
+        let Grid::BasicHex { cells, edges, .. } = &after else {
+            unreachable!();
+        };
+        assert_eq!(cells.len(), 24);
+        for (a, b) in symmetry
+            .images(a, center)
+            .into_iter()
+            .zip(symmetry.images(b, center))
+        {
+            assert_eq!(edges.edge_dir(&a, &b), Some(EdgeDir::AToB));
+        }
+        // Square grids don't have 3-fold symmetry
+        let symmetry = Symmetry {
+            fold: 3,
+            ..default()
+        };
+        assert!(symmetry.is_identity::<square::Cell>());
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let cells = [
+            hex::Cell { q: 0, r: 0 },
+            hex::Cell { q: 1, r: 0 },
+            hex::Cell { q: 0, r: 1 },
+        ];
+        let mut edges = Edges::default();
+        edges.add_one_way_edge(cells[0], cells[1]);
+        let grid = Grid::BasicHex {
+            cells: cells.into(),
+            edges,
+            layout: Layout {
+                size: 1.5,
+                orientation: Orientation::PointyTop,
+            },
+        };
+        let board: Board = grid.clone().into();
+        let (rebuilt, problems) = Grid::from_board(&board).unwrap();
+        assert!(problems.is_empty(), "{problems:?}");
+        assert!(rebuilt == grid);
+    }
+
+    #[test]
+    fn test_reconstruct_reports_unfit_cells() {
+        let cells = vec![square::Cell { x: 0, y: 0 }, square::Cell { x: 1, y: 0 }];
+        let mut board = build_board(cells, Edges::default(), Layout::new(2.0), 0.14, false);
+        board.cells_mut()[1].position += Vec2::new(0.5, 0.0);
+        let (rebuilt, problems) = Grid::from_board(&board).unwrap();
+        assert_eq!(rebuilt.len(), 1);
+        assert_eq!(problems.len(), 1);
+    }
+
+    #[test]
+    fn test_reconstruct_size_ignores_odd_first_cell() {
+        let cells = (0..3).map(|x| square::Cell { x, y: 0 }).collect();
+        let mut board = build_board(cells, Edges::default(), Layout::new(2.0), 0.14, false);
+        // Blow the first cell up, so its sides are all the wrong size
+        let cell = &mut board.cells_mut()[0];
+        for x in &mut cell.shape.points {
+            *x = cell.position + (*x - cell.position) * 1.5;
+        }
+        let (rebuilt, problems) = Grid::from_board(&board).unwrap();
+        assert_eq!(rebuilt.len(), 2);
+        assert_eq!(problems[0], "Cell 0 doesn't fit the lattice");
+    }
+
+    #[test]
+    fn test_reconstruct_reports_missing_cells() {
+        let cells = vec![square::Cell { x: 0, y: 0 }, square::Cell { x: 1, y: 0 }];
+        let mut board = build_board(cells, Edges::default(), Layout::new(2.0), 0.14, false);
+        let position = board.cells()[0].position;
+        let path = Path::simple(position, position + Vec2::X);
+        board.cells_mut()[0].neighbors.insert(7, path);
+        let (rebuilt, problems) = Grid::from_board(&board).unwrap();
+        assert_eq!(rebuilt.len(), 2);
+        assert_eq!(problems, ["Edge 0 → 7 points to a missing cell"]);
     }
 }
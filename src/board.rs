@@ -1,66 +1,416 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BinaryHeap, HashMap},
+    f32::consts::TAU,
+    iter::once,
+    sync::OnceLock,
+};
 
 use bevy::{
     ecs::system::Resource,
-    math::{Ray2d, Vec2, Vec3},
+    math::{Rect, Vec2, Vec3},
 };
-use is_odd::IsOdd;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+pub use crate::path::{Keyframe, Path};
+use crate::spatial::SpatialIndex;
+
+/// Most points [`Polygon::pole_of_inaccessibility`] measures.
+pub const MAX_POLE_PROBES: usize = 10_000;
+
 #[derive(Serialize, Deserialize, Resource, Debug, Default, Clone)]
 pub struct Board {
-    pub cells: Vec<Cell>,
+    /// Only reachable through [`Board::cells_mut`], so the spatial index can't go stale.
+    cells: Vec<Cell>,
     pub meshes: Vec<BoardMesh>,
+    /// Built on first query, and thrown away by [`Board::cells_mut`].
+    #[serde(skip)]
+    index: OnceLock<SpatialIndex>,
 }
 
 impl Board {
+    pub fn new(cells: Vec<Cell>, meshes: Vec<BoardMesh>) -> Self {
+        Self {
+            cells,
+            meshes,
+            index: OnceLock::new(),
+        }
+    }
+
+    pub fn index(&self) -> &SpatialIndex {
+        self.index.get_or_init(|| SpatialIndex::new(&self.cells))
+    }
+
+    /// Throws away the spatial index so it's rebuilt on the next query.
+    fn invalidate_index(&mut self) {
+        self.index.take();
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    /// Mutable access to the cells that also invalidates the spatial index.
+    pub fn cells_mut(&mut self) -> &mut Vec<Cell> {
+        self.invalidate_index();
+        &mut self.cells
+    }
+
+    /// The cell strictly containing `pos`, if any. Points exactly on an edge aren't in any cell;
+    /// use [`Self::pick_with_tolerance`] to resolve those.
     pub fn pick(&self, pos: Vec2) -> Option<usize> {
-        self.cells.iter().position(|x| x.shape.contains(pos))
+        self.index().pick(&self.cells, pos)
+    }
+
+    /// Like [`Self::pick`], but if `pos` is on or within `tolerance` of cell edges, picks whichever
+    /// of those cells has its position closest to `pos`.
+    pub fn pick_with_tolerance(&self, pos: Vec2, tolerance: f32) -> Option<usize> {
+        self.pick(pos).or_else(|| {
+            let area = Rect::from_center_half_size(pos, Vec2::splat(tolerance));
+            self.index()
+                .query_rect(area)
+                .into_iter()
+                .filter(|&i| self.cells[i].shape.signed_distance(pos) >= -tolerance)
+                .min_by(|&a, &b| {
+                    let distance = |i: usize| self.cells[i].position.distance_squared(pos);
+                    distance(a).total_cmp(&distance(b))
+                })
+        })
+    }
+
+    /// Cells whose bounding box overlaps `rect`.
+    pub fn query_rect(&self, rect: Rect) -> Vec<usize> {
+        self.index().query_rect(rect)
+    }
+
+    /// The cell closest to `pos`, measuring to the edge of each cell's shape.
+    pub fn nearest(&self, pos: Vec2) -> Option<usize> {
+        self.index().nearest(&self.cells, pos)
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Cell {
     pub neighbors: HashMap<usize, Path>,
     pub shape: Polygon,
     pub position: Vec2,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct BoardMesh {
     pub color: BoardColor,
     pub mesh: Mesh,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct Path(pub BTreeMap<Keyframe, Vec2>);
-
-impl Path {
-    pub fn simple(start: Vec2, end: Vec2) -> Self {
-        Self([(Keyframe(0.0), start), (Keyframe(1.0), end)].into())
-    }
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Polygon {
+    /// The outer ring.
     pub points: Vec<Vec2>,
+    /// Rings cut out of the shape, like a lake the cell surrounds.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub holes: Vec<Vec<Vec2>>,
 }
 
 impl Polygon {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self {
+            points,
+            holes: Vec::new(),
+        }
+    }
+
+    /// The outer ring followed by each hole.
+    pub fn rings(&self) -> impl Iterator<Item = &[Vec2]> + '_ {
+        once(&self.points[..]).chain(self.holes.iter().map(|x| &x[..]))
+    }
+
     fn line_segments(&self) -> impl Iterator<Item = LineSegment> + '_ {
-        self.points
-            .iter()
-            .copied()
-            .circular_tuple_windows()
-            .map(|(x, y)| LineSegment(x, y))
+        self.rings().flat_map(|ring| {
+            ring.iter()
+                .copied()
+                .circular_tuple_windows()
+                .map(|(x, y)| LineSegment(x, y))
+        })
     }
 
+    /// Whether `pos` is inside the shape or on its boundary. Points inside a hole are outside.
     pub fn contains(&self, pos: Vec2) -> bool {
-        self.line_segments()
-            .filter(|x| x.intersection(Ray2d::new(pos, Vec2::X)).is_some())
-            .count()
-            .is_odd()
+        self.locate(pos) != Containment::Outside
+    }
+
+    /// Classifies `pos` against the shape using winding numbers. Orientation tests are done in
+    /// `f64`, where products of `f32` differences are exact, so points on edges and rays through
+    /// vertices are handled consistently.
+    pub fn locate(&self, pos: Vec2) -> Containment {
+        let mut inside = false;
+        for (i, ring) in self.rings().enumerate() {
+            match ring_winding(ring, pos) {
+                None => return Containment::Boundary,
+                Some(0) => {
+                    if i == 0 {
+                        return Containment::Outside;
+                    }
+                }
+                Some(_) => {
+                    if i == 0 {
+                        inside = true;
+                    } else {
+                        return Containment::Outside;
+                    }
+                }
+            }
+        }
+        if inside {
+            Containment::Inside
+        } else {
+            Containment::Outside
+        }
+    }
+
+    /// Signed area of the outer ring, positive when it winds counter-clockwise.
+    pub fn signed_area(&self) -> f32 {
+        ring_signed_area(&self.points)
+    }
+
+    /// Area covered by the shape, not counting holes.
+    pub fn area(&self) -> f32 {
+        self.signed_area().abs()
+            - self
+                .holes
+                .iter()
+                .map(|x| ring_signed_area(x).abs())
+                .sum::<f32>()
+    }
+
+    pub fn is_ccw(&self) -> bool {
+        self.signed_area() > 0.0
+    }
+
+    /// Makes the outer ring counter-clockwise and every hole clockwise.
+    pub fn normalize_winding(&mut self) {
+        if !self.is_ccw() {
+            self.points.reverse();
+        }
+        for hole in &mut self.holes {
+            if ring_signed_area(hole) > 0.0 {
+                hole.reverse();
+            }
+        }
+    }
+
+    /// Whether the shape is convex, ignoring collinear and repeated vertices. Shapes with holes or
+    /// fewer than 3 distinct vertices never are.
+    pub fn is_convex(&self) -> bool {
+        if !self.holes.is_empty() {
+            return false;
+        }
+        let mut points = self.points.clone();
+        points.dedup();
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 3 {
+            return false;
+        }
+        let mut sign = 0.0;
+        let mut turning = 0.0;
+        for (&a, &b, &c) in points.iter().circular_tuple_windows() {
+            let (u, v) = (b - a, c - b);
+            let cross = u.perp_dot(v);
+            if cross == 0.0 {
+                // Doubling back is a spike, not a straight continuation
+                if u.dot(v) < 0.0 {
+                    return false;
+                }
+                continue;
+            }
+            if sign * cross < 0.0 {
+                return false;
+            }
+            sign = cross.signum();
+            turning += cross.atan2(u.dot(v));
+        }
+        // Stars turn the same way at every corner too, but wind around more than once
+        (turning.abs() - TAU).abs() < 1e-3
+    }
+
+    /// Box around the outer ring, or `None` if it has no points.
+    pub fn bounding_box(&self) -> Option<Rect> {
+        let (&first, rest) = self.points.split_first()?;
+        let bounds = Rect::from_corners(first, first);
+        Some(rest.iter().fold(bounds, |x, &y| x.union_point(y)))
+    }
+
+    /// Center of mass of the shape, accounting for holes. Falls back to the vertex average for
+    /// shapes with no area. This can land outside concave shapes; see
+    /// [`Self::pole_of_inaccessibility`] for a point that's always inside.
+    pub fn centroid(&self) -> Vec2 {
+        let mut area = 0.0;
+        let mut moment = Vec2::ZERO;
+        for (i, ring) in self.rings().enumerate() {
+            // Count the outer ring as positive and holes as negative, whatever their winding
+            let ring_area = ring_signed_area(ring);
+            let sign = if (i == 0) == (ring_area > 0.0) {
+                1.0
+            } else {
+                -1.0
+            };
+            area += ring_area * sign;
+            moment += ring
+                .iter()
+                .circular_tuple_windows()
+                .map(|(&a, &b)| (a + b) * a.perp_dot(b))
+                .fold(Vec2::ZERO, |x, y| x + y)
+                * sign
+                / 6.0;
+        }
+        if area.abs() <= f32::EPSILON {
+            self.points.iter().fold(Vec2::ZERO, |x, &y| x + y) / self.points.len() as f32
+        } else {
+            moment / area
+        }
+    }
+
+    /// Distance from `pos` to the nearest edge of any ring, positive inside the shape and negative
+    /// outside.
+    pub fn signed_distance(&self, pos: Vec2) -> f32 {
+        let distance = self
+            .line_segments()
+            .map(|x| x.distance(pos))
+            .fold(f32::INFINITY, f32::min);
+        if self.contains(pos) {
+            distance
+        } else {
+            -distance
+        }
+    }
+
+    /// The point inside the shape farthest from its edges (within `precision`), which makes a good
+    /// label or token position for concave shapes where the centroid falls outside. Settles for
+    /// the best point so far after [`MAX_POLE_PROBES`] probes, which only long thin shapes need.
+    pub fn pole_of_inaccessibility(&self, precision: f32) -> Vec2 {
+        let Some(bounds) = self.bounding_box() else {
+            return Vec2::ZERO;
+        };
+        let size = bounds.size().max_element();
+        if size <= 0.0 {
+            return bounds.min;
+        }
+
+        // Start from one square covering the whole shape, so long thin shapes don't start out with
+        // a huge grid
+        let probe = |center: Vec2, half: f32| Probe::new(self, center, half);
+        let mut queue = BinaryHeap::from([probe(bounds.center(), size / 2.0)]);
+        let mut probes = 1;
+
+        let mut best = probe(self.centroid(), 0.0);
+        let center = probe(bounds.center(), 0.0);
+        if center.distance > best.distance {
+            best = center;
+        }
+        while let Some(cell) = queue.pop() {
+            if cell.distance > best.distance {
+                best = cell;
+            }
+            if cell.potential - best.distance <= precision {
+                continue;
+            }
+            if probes >= MAX_POLE_PROBES {
+                break;
+            }
+            probes += 4;
+            let half = cell.half / 2.0;
+            for offset in [
+                Vec2::new(-1.0, -1.0),
+                Vec2::X - Vec2::Y,
+                Vec2::Y - Vec2::X,
+                Vec2::ONE,
+            ] {
+                queue.push(probe(cell.center + offset * half, half));
+            }
+        }
+        best.center
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Containment {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+/// Winding number of `ring` around `pos`, or `None` if `pos` lies on the ring.
+fn ring_winding(ring: &[Vec2], pos: Vec2) -> Option<i32> {
+    let p = pos.as_dvec2();
+    let mut winding = 0;
+    for (&a, &b) in ring.iter().circular_tuple_windows() {
+        let (a, b) = (a.as_dvec2(), b.as_dvec2());
+        let side = (b - a).perp_dot(p - a);
+        if side == 0.0 && p.cmpge(a.min(b)).all() && p.cmple(a.max(b)).all() {
+            return None;
+        }
+        // Half-open crossing rule: an edge counts when it spans `pos.y` from below (inclusive) to
+        // above (exclusive), so a ray through a vertex is only counted once and horizontal edges
+        // never are
+        if a.y <= p.y {
+            if b.y > p.y && side > 0.0 {
+                winding += 1;
+            }
+        } else if b.y <= p.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    Some(winding)
+}
+
+fn ring_signed_area(ring: &[Vec2]) -> f32 {
+    ring.iter()
+        .circular_tuple_windows()
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// A square search cell for [`Polygon::pole_of_inaccessibility`], ordered by the best distance
+/// any point inside it could possibly have.
+#[derive(Clone, Copy)]
+struct Probe {
+    center: Vec2,
+    half: f32,
+    distance: f32,
+    potential: f32,
+}
+
+impl Probe {
+    fn new(polygon: &Polygon, center: Vec2, half: f32) -> Self {
+        let distance = polygon.signed_distance(center);
+        Self {
+            center,
+            half,
+            distance,
+            potential: distance + half * std::f32::consts::SQRT_2,
+        }
+    }
+}
+
+impl PartialEq for Probe {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Probe {}
+
+impl PartialOrd for Probe {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Probe {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.potential.total_cmp(&other.potential)
     }
 }
 
@@ -68,8 +418,10 @@ impl std::ops::Add<Vec2> for Polygon {
     type Output = Polygon;
 
     fn add(self, rhs: Vec2) -> Self::Output {
+        let shift = |ring: Vec<Vec2>| ring.into_iter().map(|x| x + rhs).collect();
         Self {
-            points: self.points.into_iter().map(|x| x + rhs).collect(),
+            points: shift(self.points),
+            holes: self.holes.into_iter().map(shift).collect(),
         }
     }
 }
@@ -86,20 +438,24 @@ impl LineSegment {
         self.0 + self.ab() * t
     }
 
-    fn intersection(&self, ray: Ray2d) -> Option<Vec2> {
-        let t = (ray.origin - self.0).perp_dot(*ray.direction) / self.ab().perp_dot(*ray.direction);
-        let u = (self.0 - ray.origin).perp_dot(self.ab()) / ray.direction.perp_dot(self.ab());
-        (u >= 0.0 && (0.0..=1.0).contains(&t)).then(|| self.lerp(t))
+    fn distance(&self, pos: Vec2) -> f32 {
+        let t = (pos - self.0).dot(self.ab()) / self.ab().length_squared();
+        let t = if t.is_finite() {
+            t.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.lerp(t).distance(pos)
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum BoardColor {
     PlayerColor,
     StaticColor(f32, f32, f32),
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub enum Mesh {
     IndexedLineMesh {
         vertices: Vec<Vec3>,
@@ -111,13 +467,218 @@ pub enum Mesh {
     },
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, PartialOrd, Debug)]
-pub struct Keyframe(pub f32);
+#[cfg(test)]
+mod test {
+    use std::f32::consts::SQRT_2;
 
-impl Eq for Keyframe {}
+    use super::*;
 
-impl Ord for Keyframe {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.0.total_cmp(&other.0)
+    fn square() -> Polygon {
+        Polygon::new(vec![
+            Vec2::new(-1.0, -1.0),
+            Vec2::new(1.0, -1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(-1.0, 1.0),
+        ])
+    }
+
+    #[test]
+    fn test_locate() {
+        let square = square();
+        assert_eq!(square.locate(Vec2::ZERO), Containment::Inside);
+        assert_eq!(square.locate(Vec2::new(2.0, 0.0)), Containment::Outside);
+        assert_eq!(square.locate(Vec2::new(1.0, 0.0)), Containment::Boundary);
+        assert_eq!(square.locate(Vec2::new(0.0, 1.0)), Containment::Boundary);
+        assert_eq!(square.locate(Vec2::new(-1.0, -1.0)), Containment::Boundary);
+        // Rays along the top and bottom edges and through vertices
+        assert_eq!(square.locate(Vec2::new(-2.0, 1.0)), Containment::Outside);
+        assert_eq!(square.locate(Vec2::new(-2.0, -1.0)), Containment::Outside);
+    }
+
+    /// A 2 by 2 square with a 1 by 1 hole in its middle.
+    fn square_with_hole() -> Polygon {
+        let mut shape = square();
+        shape.holes.push(vec![
+            Vec2::new(-0.5, -0.5),
+            Vec2::new(-0.5, 0.5),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(0.5, -0.5),
+        ]);
+        shape
+    }
+
+    /// An L whose arms are 1 wide and 3 long, so its centroid falls in the notch.
+    fn l_shape() -> Polygon {
+        Polygon::new(vec![
+            Vec2::ZERO,
+            Vec2::new(3.0, 0.0),
+            Vec2::new(3.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(1.0, 3.0),
+            Vec2::new(0.0, 3.0),
+        ])
+    }
+
+    #[test]
+    fn test_area_and_winding() {
+        let unit = Polygon::new(vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]);
+        assert_eq!(unit.signed_area(), 1.0);
+        assert_eq!(unit.area(), 1.0);
+        assert!(unit.is_ccw());
+
+        let mut cw = square();
+        cw.points.reverse();
+        assert_eq!(cw.signed_area(), -4.0);
+        assert_eq!(cw.area(), 4.0);
+        cw.normalize_winding();
+        assert_eq!(cw, square());
+
+        let mut holed = square_with_hole();
+        assert_eq!(holed.area(), 3.0);
+        holed.points.reverse();
+        holed.holes[0].reverse();
+        assert_eq!(holed.area(), 3.0);
+        holed.normalize_winding();
+        assert_eq!(holed, square_with_hole());
+    }
+
+    #[test]
+    fn test_centroid() {
+        let unit = Polygon::new(vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]);
+        assert!(unit.centroid().distance(Vec2::splat(0.5)) < 1e-6);
+        let mut cw = square() + Vec2::ONE;
+        cw.points.reverse();
+        assert!(cw.centroid().distance(Vec2::ONE) < 1e-6);
+
+        // Cutting a hole out of one side pulls the centroid the other way
+        let mut holed = square();
+        holed
+            .holes
+            .push(vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y]);
+        assert!(holed.centroid().distance(Vec2::splat(-1.0 / 6.0)) < 1e-6);
+        holed.holes[0].reverse();
+        assert!(holed.centroid().distance(Vec2::splat(-1.0 / 6.0)) < 1e-6);
+
+        let l = l_shape();
+        assert!(l.centroid().distance(Vec2::splat(1.1)) < 1e-5);
+        assert_eq!(l.locate(l.centroid()), Containment::Outside);
+    }
+
+    #[test]
+    fn test_signed_distance() {
+        let square = square();
+        assert!((square.signed_distance(Vec2::ZERO) - 1.0).abs() < 1e-6);
+        assert!((square.signed_distance(Vec2::new(0.5, 0.0)) - 0.5).abs() < 1e-6);
+        assert!((square.signed_distance(Vec2::new(3.0, 0.0)) + 2.0).abs() < 1e-6);
+        assert!((square.signed_distance(Vec2::new(2.0, 2.0)) + SQRT_2).abs() < 1e-6);
+
+        let holed = square_with_hole();
+        assert!((holed.signed_distance(Vec2::ZERO) + 0.5).abs() < 1e-6);
+        assert!((holed.signed_distance(Vec2::new(0.75, 0.0)) - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_pole_of_inaccessibility() {
+        let pole = square().pole_of_inaccessibility(1e-3);
+        assert!(pole.length() < 1e-2);
+
+        let l = l_shape();
+        let pole = l.pole_of_inaccessibility(1e-3);
+        assert_eq!(l.locate(pole), Containment::Inside);
+        // The arms are only 1 wide, but the corner where they meet has a little more room
+        let corner = Vec2::splat(2.0 - SQRT_2);
+        assert!(pole.distance(corner) < 1e-2);
+        assert!((l.signed_distance(pole) - (2.0 - SQRT_2)).abs() < 1e-2);
+
+        let holed = square_with_hole();
+        let pole = holed.pole_of_inaccessibility(1e-3);
+        assert_eq!(holed.locate(pole), Containment::Inside);
+        assert!((holed.signed_distance(pole) - (1.0 - SQRT_2 / 2.0)).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_is_convex() {
+        assert!(square().is_convex());
+        let mut reversed = square();
+        reversed.points.reverse();
+        assert!(reversed.is_convex());
+        // Every corner of a pentagram turns the same way
+        let star = (0..5)
+            .map(|i| Vec2::from_angle(i as f32 * 2.0 * TAU / 5.0))
+            .collect::<Vec<_>>();
+        assert!(!Polygon::new(star).is_convex());
+        let line = vec![Vec2::ZERO, Vec2::X, Vec2::X, Vec2::ZERO];
+        assert!(!Polygon::new(line).is_convex());
+        let collinear = vec![Vec2::ZERO, Vec2::X, Vec2::new(2.0, 0.0)];
+        assert!(!Polygon::new(collinear).is_convex());
+        let mut repeated = square();
+        repeated.points.insert(1, repeated.points[0]);
+        repeated.points.push(repeated.points[0]);
+        assert!(repeated.is_convex());
+    }
+
+    #[test]
+    fn test_pole_of_long_thin_shape() {
+        let strip = Polygon::new(vec![
+            Vec2::ZERO,
+            Vec2::new(1000.0, 0.0),
+            Vec2::new(1000.0, 0.001),
+            Vec2::new(0.0, 0.001),
+        ]);
+        let pole = strip.pole_of_inaccessibility(1e-5);
+        assert_eq!(strip.locate(pole), Containment::Inside);
+        assert!((pole.y - 0.0005).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_locate_hole() {
+        let mut shape = square();
+        shape.holes.push(vec![
+            Vec2::new(-0.5, -0.5),
+            Vec2::new(-0.5, 0.5),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(0.5, -0.5),
+        ]);
+        assert_eq!(shape.locate(Vec2::ZERO), Containment::Outside);
+        assert_eq!(shape.locate(Vec2::new(0.75, 0.0)), Containment::Inside);
+        assert_eq!(shape.locate(Vec2::new(0.5, 0.0)), Containment::Boundary);
+    }
+
+    #[test]
+    fn test_pick_shared_edge() {
+        let cell = |offset: Vec2| Cell {
+            neighbors: HashMap::new(),
+            shape: square() + offset,
+            position: offset,
+        };
+        let board = Board::new(vec![cell(Vec2::ZERO), cell(Vec2::new(2.0, 0.0))], vec![]);
+        assert_eq!(board.pick(Vec2::new(1.0, 0.0)), None);
+        assert_eq!(
+            board.pick_with_tolerance(Vec2::new(1.0, 0.0), 0.01),
+            Some(0)
+        );
+        assert_eq!(
+            board.pick_with_tolerance(Vec2::new(1.001, 0.0), 0.01),
+            Some(1)
+        );
+        assert_eq!(board.pick_with_tolerance(Vec2::new(0.0, 1.5), 0.01), None);
+    }
+
+    #[test]
+    fn test_empty_shape() {
+        let empty = Cell {
+            neighbors: HashMap::new(),
+            shape: Polygon::new(vec![]),
+            position: Vec2::ZERO,
+        };
+        assert_eq!(empty.shape.bounding_box(), None);
+        let square = Cell {
+            shape: square(),
+            ..empty.clone()
+        };
+        let board = Board::new(vec![empty, square], vec![]);
+        assert_eq!(board.pick(Vec2::ZERO), Some(1));
+        assert_eq!(board.nearest(Vec2::new(5.0, 0.0)), Some(1));
+        assert_eq!(board.query_rect(Rect::new(-9.0, -9.0, 9.0, 9.0)), [1]);
     }
 }
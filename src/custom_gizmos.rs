@@ -1,38 +1,16 @@
-use bevy::{gizmos::gizmos::Gizmos, math::Vec2, render::color::Color};
+use bevy::{gizmos::gizmos::Gizmos, render::color::Color};
+
+use crate::board::Polygon;
 
 pub trait CustomGizmos {
-    fn square(&mut self, pos: Vec2, color: Color);
-    fn hex(&mut self, pos: Vec2, color: Color);
+    /// Outlines every ring of `polygon`.
+    fn polygon(&mut self, polygon: &Polygon, color: Color);
 }
 
 impl CustomGizmos for Gizmos<'_, '_> {
-    fn square(&mut self, pos: Vec2, color: Color) {
-        self.linestrip_2d(
-            [
-                Vec2::new(-1.0, -1.0),
-                Vec2::new(1.0, -1.0),
-                Vec2::new(1.0, 1.0),
-                Vec2::new(-1.0, 1.0),
-                Vec2::new(-1.0, -1.0),
-            ]
-            .map(|x| x + pos),
-            color,
-        )
-    }
-
-    fn hex(&mut self, pos: Vec2, color: Color) {
-        self.linestrip_2d(
-            [
-                Vec2::new(1.0, 0.0),
-                Vec2::new(0.5, 0.866),
-                Vec2::new(-0.5, 0.866),
-                Vec2::new(-1.0, 0.0),
-                Vec2::new(-0.5, -0.866),
-                Vec2::new(0.5, -0.866),
-                Vec2::new(1.0, 0.0),
-            ]
-            .map(|x| x + pos),
-            color,
-        )
+    fn polygon(&mut self, polygon: &Polygon, color: Color) {
+        for ring in polygon.rings() {
+            self.linestrip_2d(ring.iter().chain(ring.first()).copied(), color);
+        }
     }
 }
@@ -0,0 +1,64 @@
+use std::fmt::Display;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Gltf(gltf::Error),
+    /// The glTF document parsed, but is missing data we need (buffers, indices, positions...).
+    GltfStructure(String),
+    NotABoard,
+    UnsupportedVersion {
+        found: u32,
+        supported: u32,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            Error::Json(e) => write!(f, "Invalid board JSON: {e}"),
+            Error::Gltf(e) => write!(f, "Invalid glTF: {e}"),
+            Error::GltfStructure(e) => write!(f, "Malformed glTF: {e}"),
+            Error::NotABoard => write!(f, "File is not a board"),
+            Error::UnsupportedVersion { found, supported } => write!(
+                f,
+                "File format version {found} is newer than the newest supported version \
+                 ({supported}); update {}",
+                env!("CARGO_PKG_NAME")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Json(e) => Some(e),
+            Error::Gltf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<gltf::Error> for Error {
+    fn from(value: gltf::Error) -> Self {
+        Self::Gltf(value)
+    }
+}
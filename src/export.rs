@@ -1,7 +1,8 @@
 use bevy::{ecs::system::Command, prelude::*, window::PrimaryWindow, winit::WinitWindows};
 use bevy_mod_async::SpawnTaskExt;
+use rfd::FileHandle;
 
-use crate::board::Board;
+use crate::{board::Board, error::Result, format::BoardFile, notify::Notifications};
 
 pub struct ExportBoardCmd(pub Board);
 
@@ -29,10 +30,13 @@ impl Command for ExportBoardCmd {
                 .await;
             if let Some(file) = dialog.save_file().await {
                 let Self(board) = self;
-                let json = serde_json::to_string(&board).unwrap();
-                match file.write(json.as_bytes()).await {
-                    Err(e) => println!("Error writing board: {e:?}"),
-                    _ => {}
+                if let Err(e) = write_board(&file, board).await {
+                    cx.with_world(move |world| {
+                        world
+                            .resource_mut::<Notifications>()
+                            .push(format!("Error writing board: {e}"))
+                    })
+                    .await;
                 }
             };
             cx.with_world(|world| world.remove_resource::<Exporting>())
@@ -40,3 +44,9 @@ impl Command for ExportBoardCmd {
         });
     }
 }
+
+async fn write_board(file: &FileHandle, board: Board) -> Result<()> {
+    let json = BoardFile::new(board).to_json()?;
+    file.write(json.as_bytes()).await?;
+    Ok(())
+}
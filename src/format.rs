@@ -0,0 +1,166 @@
+use std::{
+    io::Read,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    board::Board,
+    error::{Error, Result},
+};
+
+/// Version of the board file format written by this build. Bump this whenever the serialized shape
+/// of [`Board`] changes, and add a matching step to [`migrate`].
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Name and version of the tool that wrote a board file.
+pub const GENERATOR: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// Envelope around a [`Board`] as it's stored on disk.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BoardFile {
+    pub format_version: u32,
+    pub generator: String,
+    /// Seconds since the Unix epoch at which the file was written.
+    pub created: u64,
+    pub board: Board,
+}
+
+impl BoardFile {
+    pub fn new(board: Board) -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |x| x.as_secs());
+        Self {
+            format_version: FORMAT_VERSION,
+            generator: GENERATOR.into(),
+            created,
+            board,
+        }
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        Self::from_value(serde_json::from_reader(reader)?)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self> {
+        Self::from_value(serde_json::from_slice(bytes)?)
+    }
+
+    /// Parses a board file of any supported version, upgrading it to the current schema.
+    pub fn from_value(value: Value) -> Result<Self> {
+        let version = version_of(&value)?;
+        if version > FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion {
+                found: version,
+                supported: FORMAT_VERSION,
+            });
+        }
+        Ok(serde_json::from_value(migrate(value, version))?)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Files written before the envelope existed are bare boards, which we call version 0.
+fn version_of(value: &Value) -> Result<u32> {
+    let Value::Object(map) = value else {
+        return Err(Error::NotABoard);
+    };
+    match map.get("format_version") {
+        Some(version) => version
+            .as_u64()
+            .and_then(|x| u32::try_from(x).ok())
+            .ok_or(Error::NotABoard),
+        None if map.contains_key("cells") => Ok(0),
+        None => Err(Error::NotABoard),
+    }
+}
+
+/// Upgrades `value` one version at a time until it matches [`FORMAT_VERSION`].
+fn migrate(mut value: Value, from: u32) -> Value {
+    for version in from..FORMAT_VERSION {
+        value = match version {
+            0 => v0_to_v1(value),
+            1 => v1_to_v2(value),
+            _ => unreachable!("No migration from format version {version}"),
+        };
+    }
+    value
+}
+
+fn v0_to_v1(board: Value) -> Value {
+    serde_json::json!({
+        "format_version": 1,
+        "generator": "unknown",
+        "created": 0,
+        "board": board,
+    })
+}
+
+/// Paths gained an interpolation mode, so the bare keyframe maps become linear paths.
+fn v1_to_v2(mut file: Value) -> Value {
+    file["format_version"] = 2.into();
+    if let Some(cells) = file["board"]["cells"].as_array_mut() {
+        let neighbors = cells
+            .iter_mut()
+            .filter_map(|x| x["neighbors"].as_object_mut());
+        for path in neighbors.flat_map(|x| x.values_mut()) {
+            *path = serde_json::json!({
+                "keyframes": path.take(),
+                "interpolation": "Linear",
+            });
+        }
+    }
+    file
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec2;
+
+    use super::*;
+    use crate::board::Path;
+
+    #[test]
+    fn test_load_legacy_board() {
+        let json = r#"{"cells":[],"meshes":[]}"#;
+        let file = BoardFile::from_slice(json.as_bytes()).unwrap();
+        assert_eq!(file.format_version, FORMAT_VERSION);
+        assert!(file.board.cells().is_empty());
+    }
+
+    #[test]
+    fn test_migrate_v1_paths() {
+        let json = r#"{"format_version":1,"generator":"","created":0,"board":{"cells":[
+            {"neighbors":{"1":{"0.0":[0.0,0.0],"1.0":[1.0,0.0]}},
+             "shape":{"points":[]},"position":[0.0,0.0]}
+        ],"meshes":[]}}"#;
+        let file = BoardFile::from_slice(json.as_bytes()).unwrap();
+        let path = &file.board.cells()[0].neighbors[&1];
+        assert_eq!(path, &Path::simple(Vec2::ZERO, Vec2::X));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let json = BoardFile::new(Board::default()).to_json().unwrap();
+        let file = BoardFile::from_slice(json.as_bytes()).unwrap();
+        assert_eq!(file.generator, GENERATOR);
+    }
+
+    #[test]
+    fn test_reject_newer_version() {
+        let json = format!(
+            r#"{{"format_version":{},"generator":"","created":0,"board":{{}}}}"#,
+            FORMAT_VERSION + 1
+        );
+        assert!(matches!(
+            BoardFile::from_slice(json.as_bytes()),
+            Err(Error::UnsupportedVersion { .. })
+        ));
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::VecDeque;
+
+use itertools::Itertools;
+
+use crate::board::Board;
+
+/// Cells each cell links to, ascending. Links to itself or to missing cells are left out.
+pub fn outgoing(board: &Board) -> Vec<Vec<usize>> {
+    let n = board.cells().len();
+    board
+        .cells()
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| {
+            let links = cell.neighbors.keys().copied();
+            links.filter(|&x| x != i && x < n).sorted().collect()
+        })
+        .collect()
+}
+
+/// Cells linked to each cell in either direction, ascending.
+pub fn undirected(board: &Board) -> Vec<Vec<usize>> {
+    let outgoing = outgoing(board);
+    let mut adjacent = outgoing.clone();
+    for (i, links) in outgoing.into_iter().enumerate() {
+        for x in links {
+            adjacent[x].push(i);
+        }
+    }
+    for links in &mut adjacent {
+        links.sort_unstable();
+        links.dedup();
+    }
+    adjacent
+}
+
+/// Fewest links it takes to get from `from` to each cell, or `None` where it can't be reached.
+pub fn distances(board: &Board, from: usize) -> Vec<Option<usize>> {
+    let outgoing = outgoing(board);
+    let mut distances = vec![None; outgoing.len()];
+    if from >= outgoing.len() {
+        return distances;
+    }
+    distances[from] = Some(0);
+    let mut queue = VecDeque::from([from]);
+    while let Some(x) = queue.pop_front() {
+        let next = distances[x].map(|d| d + 1);
+        for &y in &outgoing[x] {
+            if distances[y].is_none() {
+                distances[y] = next;
+                queue.push_back(y);
+            }
+        }
+    }
+    distances
+}
+
+/// Groups of cells that can all reach each other, following one-way links only the right way.
+/// Largest first, each sorted ascending.
+pub fn strongly_connected_components(board: &Board) -> Vec<Vec<usize>> {
+    // Tarjan's algorithm, with an explicit stack so large boards can't overflow the real one
+    let outgoing = outgoing(board);
+    let n = outgoing.len();
+    let mut index = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut next = 0;
+    let mut components = Vec::new();
+    for root in 0..n {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        let mut calls = vec![(root, 0)];
+        index[root] = next;
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some((v, child)) = calls.last_mut() {
+            let v = *v;
+            if let Some(&w) = outgoing[v].get(*child) {
+                *child += 1;
+                if index[w] == usize::MAX {
+                    index[w] = next;
+                    low[w] = next;
+                    next += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = Vec::new();
+                while let Some(x) = stack.pop() {
+                    on_stack[x] = false;
+                    component.push(x);
+                    if x == v {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components.sort_by_key(|x| (std::cmp::Reverse(x.len()), x[0]));
+    components
+}
+
+/// Cells whose removal would split the board into more pieces, ignoring link direction.
+pub fn articulation_cells(board: &Board) -> Vec<usize> {
+    let adjacent = undirected(board);
+    let n = adjacent.len();
+    let mut discovered = vec![usize::MAX; n];
+    let mut low = vec![0; n];
+    let mut cut = vec![false; n];
+    let mut time = 0;
+    for root in 0..n {
+        if discovered[root] != usize::MAX {
+            continue;
+        }
+        discovered[root] = time;
+        low[root] = time;
+        time += 1;
+        let mut root_children = 0;
+        // (cell, cell it was reached from, next neighbor to visit)
+        let mut calls = vec![(root, usize::MAX, 0)];
+        while let Some((v, parent, child)) = calls.last_mut() {
+            let (v, parent) = (*v, *parent);
+            if let Some(&w) = adjacent[v].get(*child) {
+                *child += 1;
+                if discovered[w] == usize::MAX {
+                    discovered[w] = time;
+                    low[w] = time;
+                    time += 1;
+                    if v == root {
+                        root_children += 1;
+                    }
+                    calls.push((w, v, 0));
+                } else if w != parent {
+                    low[v] = low[v].min(discovered[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(p, _, _)) = calls.last() {
+                low[p] = low[p].min(low[v]);
+                if p != root && low[v] >= discovered[p] {
+                    cut[p] = true;
+                }
+            }
+        }
+        if root_children > 1 {
+            cut[root] = true;
+        }
+    }
+    (0..n).filter(|&x| cut[x]).collect()
+}
+
+/// Connectivity of a whole board.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Analysis {
+    /// See [`strongly_connected_components`]. Every cell can reach every other when there's at
+    /// most one.
+    pub components: Vec<Vec<usize>>,
+    /// Cells with no links either way.
+    pub isolated: Vec<usize>,
+    /// Cells linked to exactly one other cell, in either direction.
+    pub dead_ends: Vec<usize>,
+    /// Cells that can be entered but have no links out.
+    pub sinks: Vec<usize>,
+    /// See [`articulation_cells`].
+    pub articulation_cells: Vec<usize>,
+    /// Total links minus one per cell, for cells with any.
+    pub capacity: usize,
+}
+
+impl Analysis {
+    pub fn new(board: &Board) -> Self {
+        let outgoing = outgoing(board);
+        let undirected = undirected(board);
+        let cells_where = |f: &dyn Fn(usize) -> bool| -> Vec<usize> {
+            (0..outgoing.len()).filter(|&x| f(x)).collect()
+        };
+        Self {
+            components: strongly_connected_components(board),
+            isolated: cells_where(&|x| undirected[x].is_empty()),
+            dead_ends: cells_where(&|x| undirected[x].len() == 1),
+            sinks: cells_where(&|x| outgoing[x].is_empty() && !undirected[x].is_empty()),
+            articulation_cells: articulation_cells(board),
+            capacity: board
+                .cells()
+                .iter()
+                .map(|x| x.neighbors.len().max(1) - 1)
+                .sum(),
+        }
+    }
+
+    pub fn strongly_connected(&self) -> bool {
+        self.components.len() <= 1
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bevy::math::Vec2;
+
+    use super::*;
+    use crate::board::{Cell, Path, Polygon};
+
+    /// Cells in a row, linked by `[from, to]` pairs.
+    fn board(cells: usize, links: &[[usize; 2]]) -> Board {
+        let mut cells = (0..cells)
+            .map(|i| Cell {
+                neighbors: HashMap::new(),
+                shape: Polygon::new(vec![Vec2::ZERO, Vec2::X, Vec2::Y]),
+                position: Vec2::new(i as f32, 0.0),
+            })
+            .collect_vec();
+        for &[a, b] in links {
+            let path = Path::simple(Vec2::ZERO, Vec2::ZERO);
+            cells[a].neighbors.insert(b, path);
+        }
+        Board::new(cells, Vec::new())
+    }
+
+    fn both_ways(links: &[[usize; 2]]) -> Vec<[usize; 2]> {
+        links.iter().flat_map(|&[a, b]| [[a, b], [b, a]]).collect()
+    }
+
+    #[test]
+    fn test_components_and_distances() {
+        // A two-way loop 0-1-2, a one-way link out to 3, and 3 <-> 4
+        let mut links = both_ways(&[[0, 1], [1, 2], [2, 0], [3, 4]]);
+        links.extend([[2, 3], [2, 9], [4, 4]]);
+        let board = board(6, &links);
+        assert_eq!(
+            strongly_connected_components(&board),
+            [vec![0, 1, 2], vec![3, 4], vec![5]]
+        );
+        assert_eq!(
+            distances(&board, 0),
+            [Some(0), Some(1), Some(1), Some(2), Some(3), None]
+        );
+        assert_eq!(distances(&board, 3)[..3], [None, None, None]);
+
+        let analysis = Analysis::new(&board);
+        assert!(!analysis.strongly_connected());
+        assert_eq!(analysis.isolated, [5]);
+        assert_eq!(analysis.dead_ends, [4]);
+        assert_eq!(analysis.articulation_cells, [2, 3]);
+    }
+
+    #[test]
+    fn test_sinks() {
+        let cycle = board(3, &[[0, 1], [1, 2], [2, 1]]);
+        assert_eq!(Analysis::new(&cycle).sinks, Vec::<usize>::new());
+        let one_way = board(3, &[[0, 1], [1, 0], [1, 2]]);
+        assert_eq!(Analysis::new(&one_way).sinks, [2]);
+    }
+
+    #[test]
+    fn test_articulation_cells() {
+        // Two squares sharing cell 3, plus a tail off cell 0
+        let links = both_ways(&[
+            [0, 1],
+            [1, 2],
+            [2, 3],
+            [3, 0],
+            [3, 4],
+            [4, 5],
+            [5, 6],
+            [6, 3],
+            [0, 7],
+        ]);
+        assert_eq!(articulation_cells(&board(8, &links)), [0, 3]);
+        let ring = both_ways(&[[0, 1], [1, 2], [2, 3], [3, 0]]);
+        assert!(articulation_cells(&board(4, &ring)).is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+use bevy::{
+    ecs::system::Resource,
+    input::{keyboard::KeyCode, ButtonInput},
+};
+
+/// A reversible change to some state.
+pub trait Edit {
+    type Target;
+
+    fn apply(&mut self, target: &mut Self::Target);
+    /// Reverts [`Edit::apply`]. Only ever called right after applying the edit (or redoing it), so
+    /// the target is in the state the edit left it in.
+    fn undo(&mut self, target: &mut Self::Target);
+    fn describe(&self) -> String;
+    /// Folds `next` into `self` if they're part of the same change, like the frames of a drag, so
+    /// they undo in one step. `next` has already been applied.
+    fn merge(&mut self, _next: &Self) -> bool {
+        false
+    }
+}
+
+/// Undo and redo stacks of [`Edit`]s.
+#[derive(Resource, Debug)]
+pub struct History<E> {
+    done: Vec<E>,
+    undone: Vec<E>,
+    /// Stops the next edit from merging into the last one.
+    sealed: bool,
+}
+
+impl<E> Default for History<E> {
+    fn default() -> Self {
+        Self {
+            done: Vec::new(),
+            undone: Vec::new(),
+            sealed: true,
+        }
+    }
+}
+
+impl<E: Edit> History<E> {
+    /// Applies `edit` and records it, dropping anything that could have been redone.
+    pub fn push(&mut self, target: &mut E::Target, mut edit: E) {
+        edit.apply(target);
+        self.record(edit);
+    }
+
+    /// Records an edit that has already been applied.
+    pub fn record(&mut self, edit: E) {
+        self.undone.clear();
+        let merged = !self.sealed && self.done.last_mut().is_some_and(|x| x.merge(&edit));
+        if !merged {
+            self.done.push(edit);
+        }
+        self.sealed = false;
+    }
+
+    /// Ends the current change; the next edit starts a new undo step.
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    pub fn undo(&mut self, target: &mut E::Target) -> bool {
+        let Some(mut edit) = self.done.pop() else {
+            return false;
+        };
+        edit.undo(target);
+        self.undone.push(edit);
+        self.sealed = true;
+        true
+    }
+
+    pub fn redo(&mut self, target: &mut E::Target) -> bool {
+        let Some(mut edit) = self.undone.pop() else {
+            return false;
+        };
+        edit.apply(target);
+        self.done.push(edit);
+        self.sealed = true;
+        true
+    }
+
+    /// Applied edits, oldest first.
+    pub fn done(&self) -> &[E] {
+        &self.done
+    }
+
+    /// Undone edits, most recently undone last.
+    pub fn undone(&self) -> &[E] {
+        &self.undone
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shortcut {
+    Undo,
+    Redo,
+}
+
+/// Ctrl+Z undoes, and Ctrl+Shift+Z or Ctrl+Y redoes.
+pub fn shortcut(keys: &ButtonInput<KeyCode>) -> Option<Shortcut> {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return None;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keys.just_pressed(KeyCode::KeyZ) && !shift {
+        Some(Shortcut::Undo)
+    } else if keys.just_pressed(KeyCode::KeyZ) || keys.just_pressed(KeyCode::KeyY) {
+        Some(Shortcut::Redo)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Add(i32);
+
+    impl Edit for Add {
+        type Target = i32;
+
+        fn apply(&mut self, target: &mut i32) {
+            *target += self.0;
+        }
+
+        fn undo(&mut self, target: &mut i32) {
+            *target -= self.0;
+        }
+
+        fn describe(&self) -> String {
+            format!("Add {}", self.0)
+        }
+
+        fn merge(&mut self, next: &Self) -> bool {
+            self.0 += next.0;
+            true
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut history = History::default();
+        let mut x = 0;
+        history.push(&mut x, Add(1));
+        history.seal();
+        history.push(&mut x, Add(2));
+        assert!(history.undo(&mut x));
+        assert_eq!(x, 1);
+        assert!(history.redo(&mut x));
+        assert_eq!(x, 3);
+        assert!(!history.redo(&mut x));
+        history.undo(&mut x);
+        history.push(&mut x, Add(10));
+        assert!(history.undone().is_empty());
+        assert_eq!(x, 11);
+    }
+
+    #[test]
+    fn test_merge_until_sealed() {
+        let mut history = History::default();
+        let mut x = 0;
+        history.push(&mut x, Add(1));
+        history.push(&mut x, Add(1));
+        history.seal();
+        history.push(&mut x, Add(1));
+        assert_eq!(history.done().len(), 2);
+        history.undo(&mut x);
+        history.undo(&mut x);
+        assert_eq!(x, 0);
+    }
+}
@@ -1,207 +1,603 @@
-use crate::board::{Cell, Mesh, Path, Polygon};
-use bevy::{
-    math::{Vec2, Vec3, Vec3Swizzles},
-    utils::FloatOrd,
+use crate::{
+    board::{Cell, Mesh, Path, Polygon},
+    error::{Error, Result},
+    planar::{extract_faces, signed_area, Diagnostic, PlanarFaces},
 };
-use gltf::{mesh::Mode, Gltf, Primitive, Semantic};
+use bevy::math::{Mat4, Vec2, Vec3, Vec3Swizzles};
+use gltf::{buffer, mesh::Mode, Document, Gltf, Primitive};
 use itertools::Itertools;
-use std::{
-    collections::{HashMap, HashSet, VecDeque},
-    iter::once,
-};
 
-#[derive(Debug, Clone)]
-struct IndexedLineMesh {
-    vertices: Vec<Vec3>,
-    lines: Vec<[usize; 2]>,
+/// Reads one primitive into a board mesh, going through glTF accessors so any index component
+/// type, byte stride or sparse accessor works. Strips, loops and fans are unrolled into plain
+/// line/triangle lists. Returns `None` for point primitives, which can't be part of a board.
+fn read_primitive(prim: &Primitive, buffers: &[buffer::Data]) -> Result<Option<Mesh>> {
+    let reader = prim.reader(|buffer| buffers.get(buffer.index()).map(|x| &x[..]));
+    let vertices = reader
+        .read_positions()
+        .ok_or_else(|| Error::GltfStructure("primitive has no readable POSITION".into()))?
+        .map(Vec3::from)
+        .collect::<Vec<_>>();
+    let indices = match prim.indices() {
+        Some(_) => reader
+            .read_indices()
+            .ok_or_else(|| Error::GltfStructure("primitive has unreadable indices".into()))?
+            .into_u32()
+            .map(|x| x as usize)
+            .collect::<Vec<_>>(),
+        None => (0..vertices.len()).collect(),
+    };
+    if let Some(i) = indices.iter().find(|&&x| x >= vertices.len()) {
+        return Err(Error::GltfStructure(format!(
+            "index {i} is out of range for {} vertices",
+            vertices.len()
+        )));
+    }
+
+    let mesh = match prim.mode() {
+        Mode::Points => return Ok(None),
+        Mode::Lines => lines(indices.chunks_exact(2).map(|x| [x[0], x[1]]), vertices),
+        Mode::LineStrip => lines(indices.windows(2).map(|x| [x[0], x[1]]), vertices),
+        Mode::LineLoop => {
+            let closing = (indices.len() > 2).then(|| [indices[indices.len() - 1], indices[0]]);
+            let lines_iter = indices.windows(2).map(|x| [x[0], x[1]]).chain(closing);
+            lines(lines_iter, vertices)
+        }
+        Mode::Triangles => tris(
+            indices.chunks_exact(3).map(|x| [x[0], x[1], x[2]]),
+            vertices,
+        ),
+        Mode::TriangleStrip => {
+            // Every other triangle in a strip has flipped winding
+            let tris_iter = indices.windows(3).enumerate().map(|(i, x)| {
+                if i % 2 == 0 {
+                    [x[0], x[1], x[2]]
+                } else {
+                    [x[1], x[0], x[2]]
+                }
+            });
+            tris(tris_iter, vertices)
+        }
+        Mode::TriangleFan => {
+            let tris_iter = indices
+                .iter()
+                .skip(1)
+                .tuple_windows()
+                .map(|(&b, &c)| [indices[0], b, c]);
+            tris(tris_iter, vertices)
+        }
+    };
+    Ok(Some(mesh))
 }
 
-impl IndexedLineMesh {
-    fn neighbors(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
-        self.lines
-            .iter()
-            .filter(move |x| x.iter().contains(&v))
-            .map(move |&[x, y]| if x == v { y } else { x })
+fn lines(lines: impl Iterator<Item = [usize; 2]>, vertices: Vec<Vec3>) -> Mesh {
+    Mesh::IndexedLineMesh {
+        vertices,
+        lines: lines.filter(|[a, b]| a != b).collect(),
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct Loop(Vec<usize>);
-
-impl Loop {
-    fn new(vertices: impl Into<VecDeque<usize>>) -> Self {
-        let mut vertices = vertices.into();
-        let min = vertices.iter().position_min().unwrap();
-        vertices.rotate_left(min);
-        Self(vertices.into_iter().collect())
+/// Drops degenerate triangles, which strips use to restart.
+fn tris(triangles: impl Iterator<Item = [usize; 3]>, vertices: Vec<Vec3>) -> Mesh {
+    Mesh::IndexedTriMesh {
+        vertices,
+        triangles: triangles
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .collect(),
     }
+}
 
-    fn edges(&self) -> impl Iterator<Item = [usize; 2]> + '_ {
-        self.0
-            .iter()
-            .chain(once(self.0.first().unwrap()))
-            .tuple_windows::<(&usize, &usize)>()
-            .map(|(&x, &y)| [x.min(y), x.max(y)])
+/// A mesh pulled out of a glTF scene, in world space, named after the node it's attached to.
+#[derive(Debug, Clone)]
+pub struct ImportedMesh {
+    pub name: String,
+    pub mesh: Mesh,
+}
+
+/// Cells built from one of the imported line meshes.
+#[derive(Debug, Clone)]
+pub struct ImportedBoard {
+    pub name: String,
+    pub cells: Vec<Cell>,
+    /// Problems found while extracting faces from the line mesh.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Import {
+    pub boards: Vec<ImportedBoard>,
+    pub meshes: Vec<ImportedMesh>,
+    /// Parts of the file that were skipped, like point primitives, which boards have no use for.
+    pub diagnostics: Vec<String>,
+}
+
+/// Imports a `.glb` or `.gltf` file. Buffers of a `.gltf` may be embedded as `data:` URIs or
+/// stored in files next to it.
+pub fn import_file(path: &std::path::Path) -> Result<Import> {
+    let Gltf { document, blob } = Gltf::open(path)?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)?;
+    process_gltf(&document, &buffers)
+}
+
+pub fn process_gltf(gltf: &Document, buffers: &[buffer::Data]) -> Result<Import> {
+    let mut import = Import::default();
+    match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+        Some(scene) => {
+            for node in scene.nodes() {
+                visit_node(node, Mat4::IDENTITY, buffers, &mut import)?;
+            }
+        }
+        // Scenes are optional, in which case there's no hierarchy to speak of
+        None => {
+            for mesh in gltf.meshes() {
+                let name = mesh
+                    .name()
+                    .map_or_else(|| format!("Mesh {}", mesh.index()), Into::into);
+                read_mesh(mesh, name, Mat4::IDENTITY, buffers, &mut import)?;
+            }
+        }
     }
 
-    fn perimeter(&self, vertices: &[Vec3]) -> f32 {
-        self.edges()
-            .map(|[a, b]| vertices[a].distance(vertices[b]))
-            .sum()
+    // Open line meshes (roads, border strokes...) are only decoration, not boards
+    for mesh in &import.meshes {
+        let Mesh::IndexedLineMesh { vertices, lines } = &mesh.mesh else {
+            continue;
+        };
+        match extract_cells(vertices, lines) {
+            Some((cells, diagnostics)) => import.boards.push(ImportedBoard {
+                name: mesh.name.clone(),
+                cells,
+                diagnostics,
+            }),
+            None => import.diagnostics.push(format!(
+                "{} has no closed loops, so it's only a mesh",
+                mesh.name
+            )),
+        }
     }
-}
 
-fn lines<'a>(x: &Primitive, blob: &'a [u8]) -> impl Iterator<Item = [usize; 2]> + 'a {
-    let accessor = x.indices().unwrap();
-    let view = accessor.view().unwrap();
-    let start = accessor.offset() + view.offset();
-    let end = start + accessor.count() * accessor.size();
-    bytemuck::cast_slice::<_, [u16; 2]>(&blob[start..end])
-        .iter()
-        .map(|&[a, b]| [a as usize, b as usize])
+    Ok(import)
 }
 
-fn tris<'a>(x: &Primitive, blob: &'a [u8]) -> impl Iterator<Item = [usize; 3]> + 'a {
-    let accessor = x.indices().unwrap();
-    let view = accessor.view().unwrap();
-    let start = accessor.offset() + view.offset();
-    let end = start + accessor.count() * accessor.size();
-    bytemuck::cast_slice::<_, [u16; 3]>(&blob[start..end])
-        .iter()
-        .map(|&[a, b, c]| [a as usize, b as usize, c as usize])
+/// Collects every mesh under `node` (including `node` itself), with vertices transformed into
+/// scene space. A mesh instanced by several nodes is emitted once per node.
+fn visit_node(
+    node: gltf::Node,
+    parent: Mat4,
+    buffers: &[buffer::Data],
+    out: &mut Import,
+) -> Result<()> {
+    let transform = parent * Mat4::from_cols_array_2d(&node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        let name = node
+            .name()
+            .or(mesh.name())
+            .map_or_else(|| format!("Node {}", node.index()), Into::into);
+        read_mesh(mesh, name, transform, buffers, out)?;
+    }
+    for child in node.children() {
+        visit_node(child, transform, buffers, out)?;
+    }
+    Ok(())
 }
 
-fn positions<'a>(x: &Primitive, blob: &'a [u8]) -> impl Iterator<Item = Vec3> + 'a {
-    let accessor = x.get(&Semantic::Positions).unwrap();
-    let view = accessor.view().unwrap();
-    let start = accessor.offset() + view.offset();
-    let end = start + accessor.count() * accessor.size();
-    bytemuck::cast_slice(&blob[start..end]).iter().copied()
+fn read_mesh(
+    mesh: gltf::Mesh,
+    name: String,
+    transform: Mat4,
+    buffers: &[buffer::Data],
+    out: &mut Import,
+) -> Result<()> {
+    let multiple = mesh.primitives().len() > 1;
+    for prim in mesh.primitives() {
+        let name = if multiple {
+            format!("{name} #{}", prim.index())
+        } else {
+            name.clone()
+        };
+        let Some(mut board_mesh) = read_primitive(&prim, buffers)? else {
+            out.diagnostics
+                .push(format!("Skipped {name}: point meshes can't be loaded"));
+            continue;
+        };
+        transform_mesh(&mut board_mesh, transform);
+        out.meshes.push(ImportedMesh {
+            name,
+            mesh: board_mesh,
+        });
+    }
+    Ok(())
 }
 
-pub fn process_gltf(gltf: Gltf) -> (Vec<Vec<Cell>>, Vec<Mesh>) {
-    let blob = &gltf.blob.as_ref().unwrap()[..];
-    let mut meshes = Vec::new();
-    for mesh in gltf.meshes() {
-        for prim in mesh.primitives() {
-            let vertices = positions(&prim, blob).collect();
-            let mesh = match prim.mode() {
-                Mode::Lines => {
-                    let lines = lines(&prim, blob).collect();
-                    Mesh::IndexedLineMesh { vertices, lines }
-                }
-                Mode::LineLoop => todo!(),
-                Mode::LineStrip => todo!(),
-                Mode::Triangles => {
-                    let triangles = tris(&prim, blob).collect();
-                    Mesh::IndexedTriMesh {
-                        vertices,
-                        triangles,
-                    }
-                }
-                Mode::TriangleStrip => todo!(),
-                Mode::TriangleFan => todo!(),
-                Mode::Points => {
-                    eprintln!("Can't load point meshes");
-                    continue;
+fn transform_mesh(mesh: &mut Mesh, transform: Mat4) {
+    match mesh {
+        Mesh::IndexedLineMesh { vertices, .. } => {
+            for v in vertices {
+                *v = transform.transform_point3(*v);
+            }
+        }
+        Mesh::IndexedTriMesh {
+            vertices,
+            triangles,
+        } => {
+            for v in vertices {
+                *v = transform.transform_point3(*v);
+            }
+            // Mirroring turns triangles inside out
+            if transform.determinant() < 0.0 {
+                for tri in triangles {
+                    tri.swap(1, 2);
                 }
-            };
-            meshes.push(mesh);
+            }
         }
     }
+}
 
-    let boards = meshes
+/// Builds cells from the faces of a line mesh. Returns `None` if the lines don't close any faces.
+fn extract_cells(vertices: &[Vec3], lines: &[[usize; 2]]) -> Option<(Vec<Cell>, Vec<Diagnostic>)> {
+    let vertices = vertices.iter().map(|x| x.xy()).collect::<Vec<_>>();
+    let PlanarFaces {
+        faces,
+        neighbors,
+        boundaries,
+        diagnostics,
+    } = extract_faces(&vertices, lines);
+    if faces.is_empty() {
+        return None;
+    }
+    let ring = |x: &[usize]| x.iter().map(|&x| vertices[x]).collect::<Vec<_>>();
+    let mut shapes = faces
         .iter()
-        .filter_map(|x| match x {
-            Mesh::IndexedLineMesh { vertices, lines } => Some(IndexedLineMesh {
-                vertices: vertices.clone(),
-                lines: lines.clone(),
-            }),
-            Mesh::IndexedTriMesh { .. } => None,
-        })
-        .map(|mesh| {
-            let mut loops = HashSet::new();
-            for v in 0..mesh.vertices.len() {
-                for next in mesh.neighbors(v) {
-                    let mut current = VecDeque::new();
-                    let mut a = v;
-                    let mut b = next;
-                    let mut seen = HashSet::new();
-
-                    loop {
-                        current.push_back(a);
-                        if b == v {
-                            loops.insert(Loop::new(current));
-                            break;
-                        }
-                        // Protection from infinite loops for line meshes that don't have only loops
-                        if seen.contains(&a) {
-                            // Not a loop :(
-                            break;
-                        }
-                        seen.insert(a);
-                        let ab = mesh.vertices[b].xy() - mesh.vertices[a].xy();
-                        let c = mesh
-                            .neighbors(b)
-                            .filter(|&x| x != a)
-                            .max_by_key(|&x| {
-                                let bx = mesh.vertices[x].xy() - mesh.vertices[b].xy();
-                                FloatOrd(ab.angle_between(bx))
-                            })
-                            .unwrap();
-                        a = b;
-                        b = c;
-                    }
-                }
+        .map(|x| Polygon::new(ring(x)))
+        .collect::<Vec<_>>();
+
+    // A component sitting inside another component's face (an island in a lake) cuts a hole in the
+    // smallest face around it
+    for boundary in &boundaries {
+        let inside = boundary
+            .iter()
+            .map(|&x| vertices[x])
+            .fold(Vec2::ZERO, |acc, x| acc + x)
+            / boundary.len() as f32;
+        let probe = vertices[boundary[0]].lerp(inside, 1e-3);
+        let container = faces
+            .iter()
+            .enumerate()
+            .filter(|(_, face)| !face.iter().any(|x| boundary.contains(x)))
+            .filter(|&(i, _)| shapes[i].contains(probe))
+            .min_by(|(_, a), (_, b)| {
+                signed_area(&vertices, a).total_cmp(&signed_area(&vertices, b))
+            });
+        if let Some((i, _)) = container {
+            shapes[i].holes.push(ring(boundary));
+        }
+    }
+    let positions = shapes
+        .iter()
+        .map(|x| {
+            let centroid = x.centroid();
+            if x.contains(centroid) {
+                centroid
+            } else {
+                let size = x.bounding_box().map_or(0.0, |x| x.size().min_element());
+                x.pole_of_inaccessibility(size * 0.01)
             }
-            let max_loop_perimeter = loops
-                .iter()
-                .map(|x| FloatOrd(x.perimeter(&mesh.vertices)))
-                .max()
-                .unwrap();
-            loops.retain(|x| FloatOrd(x.perimeter(&mesh.vertices)) < max_loop_perimeter);
-            let loops = loops.into_iter().collect::<Vec<_>>();
-            println!("{} loops: {loops:?}", loops.len());
-            let shapes = loops
-                .iter()
-                .map(|x| Polygon {
-                    points: x.0.iter().map(|&x| mesh.vertices[x].xy()).collect(),
-                })
-                .collect::<Vec<_>>();
-            let positions = shapes
-                .iter()
-                .map(|x| x.points.iter().fold(Vec2::ZERO, |x, &y| x + y) / x.points.len() as f32)
-                .collect::<Vec<_>>();
-            let meta = shapes.iter().zip(&positions);
+        })
+        .collect::<Vec<_>>();
 
-            loops
-                .iter()
-                .zip(meta)
-                .map(|(l, (shape, &position))| {
-                    let edges = l.edges().collect::<HashSet<_>>();
-                    let neighbors = loops.iter().positions(|x| {
-                        let neighbor_edges = x.edges().collect::<HashSet<_>>();
-                        if edges.difference(&neighbor_edges).count() == 0 {
-                            false
-                        } else {
-                            neighbor_edges.intersection(&edges).count() > 0
-                        }
-                    });
-                    let neighbors = neighbors
-                        .map(|x| {
-                            let neighbor_position = positions[x];
-                            (x, Path::simple(position, neighbor_position))
-                        })
-                        .collect::<HashMap<_, _>>();
-                    Cell {
-                        neighbors,
-                        shape: shape.clone(),
-                        position,
-                    }
-                })
-                .collect()
+    let cells = shapes
+        .into_iter()
+        .zip(neighbors)
+        .enumerate()
+        .map(|(i, (shape, neighbors))| {
+            let position = positions[i];
+            let neighbors = neighbors
+                .into_iter()
+                .map(|x| (x, Path::simple(position, positions[x])))
+                .collect();
+            Cell {
+                neighbors,
+                shape,
+                position,
+            }
         })
         .collect();
+    Some((cells, diagnostics))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::board::Containment;
+
+    /// A document with one mesh, whose only primitive draws `positions` in glTF `mode`, through
+    /// `indices` of glTF component type `index_type` if given. Keys in `extra`, like scenes and
+    /// nodes, are added to the document as they are.
+    fn document(
+        positions: &[[f32; 3]],
+        indices: Option<(u32, &[u32])>,
+        mode: u32,
+        extra: Value,
+    ) -> (Document, Vec<buffer::Data>) {
+        let mut bytes = positions
+            .iter()
+            .flatten()
+            .flat_map(|x| x.to_le_bytes())
+            .collect_vec();
+        let positions_length = bytes.len();
+        let mut views = vec![json!({"buffer": 0, "byteLength": positions_length})];
+        let (min, max) = positions
+            .iter()
+            .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &x| {
+                (min.min(x.into()), max.max(x.into()))
+            });
+        let mut accessors = vec![json!({
+            "bufferView": 0,
+            "componentType": 5126,
+            "count": positions.len(),
+            "type": "VEC3",
+            "min": min.to_array(),
+            "max": max.to_array(),
+        })];
+        let mut primitive = json!({"attributes": {"POSITION": 0}, "mode": mode});
+        if let Some((index_type, indices)) = indices {
+            let width = match index_type {
+                5121 => 1,
+                5123 => 2,
+                _ => 4,
+            };
+            bytes.extend(
+                indices
+                    .iter()
+                    .flat_map(|x| x.to_le_bytes().into_iter().take(width)),
+            );
+            views.push(json!({
+                "buffer": 0,
+                "byteOffset": positions_length,
+                "byteLength": indices.len() * width,
+            }));
+            accessors.push(json!({
+                "bufferView": 1,
+                "componentType": index_type,
+                "count": indices.len(),
+                "type": "SCALAR",
+            }));
+            primitive["indices"] = json!(1);
+        }
+        let mut root = json!({
+            "asset": {"version": "2.0"},
+            "buffers": [{"byteLength": bytes.len()}],
+            "bufferViews": views,
+            "accessors": accessors,
+            "meshes": [{"primitives": [primitive]}],
+        });
+        for (key, value) in extra.as_object().into_iter().flatten() {
+            root[key] = value.clone();
+        }
+        let gltf = Gltf::from_slice(&serde_json::to_vec(&root).unwrap()).unwrap();
+        (gltf.document, vec![buffer::Data(bytes)])
+    }
+
+    /// Reads the only primitive of a [`document`] without a scene.
+    fn read(
+        positions: &[[f32; 3]],
+        indices: Option<(u32, &[u32])>,
+        mode: u32,
+    ) -> Result<Option<Mesh>> {
+        let (document, buffers) = document(positions, indices, mode, json!({}));
+        let primitive = document
+            .meshes()
+            .next()
+            .unwrap()
+            .primitives()
+            .next()
+            .unwrap();
+        read_primitive(&primitive, &buffers)
+    }
+
+    fn line_indices(mesh: Option<Mesh>) -> Vec<[usize; 2]> {
+        match mesh {
+            Some(Mesh::IndexedLineMesh { lines, .. }) => lines,
+            x => panic!("expected a line mesh, got {x:?}"),
+        }
+    }
+
+    fn triangle_indices(mesh: Option<Mesh>) -> Vec<[usize; 3]> {
+        match mesh {
+            Some(Mesh::IndexedTriMesh { triangles, .. }) => triangles,
+            x => panic!("expected a triangle mesh, got {x:?}"),
+        }
+    }
+
+    const FIVE: [[f32; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+        [0.0, 2.0, 0.0],
+    ];
+
+    const LINES: u32 = 1;
+    const LINE_LOOP: u32 = 2;
+    const LINE_STRIP: u32 = 3;
+    const TRIANGLES: u32 = 4;
+    const TRIANGLE_STRIP: u32 = 5;
+    const TRIANGLE_FAN: u32 = 6;
 
-    (boards, meshes)
+    #[test]
+    fn test_read_primitive() {
+        let strip = line_indices(read(&FIVE[..4], None, LINE_STRIP).unwrap());
+        assert_eq!(strip, [[0, 1], [1, 2], [2, 3]]);
+        let closed = line_indices(read(&FIVE[..4], None, LINE_LOOP).unwrap());
+        assert_eq!(closed, [[0, 1], [1, 2], [2, 3], [3, 0]]);
+
+        // Every other triangle of a strip is flipped back to the winding of the first
+        let strip = triangle_indices(read(&FIVE, None, TRIANGLE_STRIP).unwrap());
+        assert_eq!(strip, [[0, 1, 2], [2, 1, 3], [2, 3, 4]]);
+        let fan = triangle_indices(read(&FIVE, None, TRIANGLE_FAN).unwrap());
+        assert_eq!(fan, [[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        // Repeated indices restart a strip without drawing anything in between, as long as the new
+        // strip starts at an even position
+        let restarted = [0, 1, 2, 2, 2, 3, 3, 4, 0];
+        let strip = read(&FIVE, Some((5123, &restarted)), TRIANGLE_STRIP).unwrap();
+        assert_eq!(triangle_indices(strip), [[0, 1, 2], [3, 4, 0]]);
+
+        for index_type in [5121, 5123, 5125] {
+            let indices = [4, 3, 2, 0, 1, 2];
+            let mesh = read(&FIVE, Some((index_type, &indices)), TRIANGLES).unwrap();
+            assert_eq!(triangle_indices(mesh), [[4, 3, 2], [0, 1, 2]]);
+        }
+
+        assert!(read(&FIVE, Some((5123, &[0, 1, 9])), TRIANGLES).is_err());
+        assert_eq!(read(&FIVE, None, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_skip_points() {
+        let (document, buffers) = document(&FIVE, None, 0, json!({}));
+        let import = process_gltf(&document, &buffers).unwrap();
+        assert!(import.meshes.is_empty());
+        assert_eq!(
+            import.diagnostics,
+            ["Skipped Mesh 0: point meshes can't be loaded"]
+        );
+    }
+
+    #[test]
+    fn test_open_line_mesh() {
+        // A polyline that never closes, like a road drawn over the board
+        let (document, buffers) = document(&FIVE, None, 3, json!({}));
+        let import = process_gltf(&document, &buffers).unwrap();
+        assert!(import.boards.is_empty());
+        assert_eq!(import.meshes.len(), 1);
+        assert_eq!(
+            import.diagnostics,
+            ["Mesh 0 has no closed loops, so it's only a mesh"]
+        );
+    }
+
+    #[test]
+    fn test_visit_node() {
+        // The triangle is drawn by a mirrored node nested in a moved one, and again by itself
+        let scene = json!({
+            "scenes": [{"nodes": [0, 2]}],
+            "nodes": [
+                {"name": "Moved", "translation": [10.0, 0.0, 0.0], "children": [1]},
+                {
+                    "name": "Mirrored",
+                    "mesh": 0,
+                    "translation": [0.0, 5.0, 0.0],
+                    "scale": [-1.0, 1.0, 1.0],
+                },
+                {"name": "Plain", "mesh": 0},
+            ],
+        });
+        let (document, buffers) = document(&FIVE[..3], None, TRIANGLES, scene);
+        let import = process_gltf(&document, &buffers).unwrap();
+        assert!(import.boards.is_empty());
+        let [mirrored, plain] = &import.meshes[..] else {
+            panic!("expected 2 meshes, got {:?}", import.meshes);
+        };
+
+        assert_eq!(mirrored.name, "Mirrored");
+        let Mesh::IndexedTriMesh {
+            vertices,
+            triangles,
+        } = &mirrored.mesh
+        else {
+            panic!("expected a triangle mesh");
+        };
+        let expected = [
+            Vec3::new(10.0, 5.0, 0.0),
+            Vec3::new(9.0, 5.0, 0.0),
+            Vec3::new(9.0, 6.0, 0.0),
+        ];
+        for (&v, e) in vertices.iter().zip(expected) {
+            assert!(v.distance(e) < 1e-5, "{v} != {e}");
+        }
+        // Mirroring flips the winding, so it's flipped back to keep the front facing out
+        assert_eq!(triangles, &[[0, 2, 1]]);
+
+        assert_eq!(plain.name, "Plain");
+        assert_eq!(
+            plain.mesh,
+            Mesh::IndexedTriMesh {
+                vertices: FIVE[..3].iter().map(|&x| Vec3::from(x)).collect(),
+                triangles: vec![[0, 1, 2]],
+            }
+        );
+    }
+
+    #[test]
+    fn test_import_data_uri() {
+        // A unit square drawn as a line loop, with its buffer embedded in the file
+        let gltf = r#"{
+            "asset": {"version": "2.0"},
+            "buffers": [{
+                "byteLength": 48,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAACAPwAAgD8AAAAAAAAAAAAAgD8AAAAA"
+            }],
+            "bufferViews": [{"buffer": 0, "byteLength": 48}],
+            "accessors": [{
+                "bufferView": 0,
+                "componentType": 5126,
+                "count": 4,
+                "type": "VEC3",
+                "min": [0, 0, 0],
+                "max": [1, 1, 0]
+            }],
+            "meshes": [{"name": "Square", "primitives": [{"attributes": {"POSITION": 0}, "mode": 2}]}]
+        }"#;
+        let path = std::env::temp_dir().join(format!("data-uri-{}.gltf", std::process::id()));
+        std::fs::write(&path, gltf).unwrap();
+        let import = import_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let import = import.unwrap();
+        assert_eq!(import.meshes.len(), 1);
+        let [board] = &import.boards[..] else {
+            panic!("expected 1 board, got {:?}", import.boards);
+        };
+        assert_eq!(board.name, "Square");
+        assert_eq!(board.cells.len(), 1);
+        assert!((board.cells[0].shape.area() - 1.0).abs() < 1e-6);
+        assert!(board.cells[0].position.distance(Vec2::splat(0.5)) < 1e-6);
+    }
+
+    #[test]
+    fn test_ring_inside_ring() {
+        // A 4 by 4 square with a separate 2 by 2 square in its middle
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [4.0, 0.0, 0.0],
+            [4.0, 4.0, 0.0],
+            [0.0, 4.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [3.0, 1.0, 0.0],
+            [3.0, 3.0, 0.0],
+            [1.0, 3.0, 0.0],
+        ];
+        let lines = [0, 1, 1, 2, 2, 3, 3, 0, 4, 5, 5, 6, 6, 7, 7, 4];
+        let (document, buffers) = document(&positions, Some((5123, &lines)), LINES, json!({}));
+        let import = process_gltf(&document, &buffers).unwrap();
+        let cells = &import.boards[0].cells;
+        assert_eq!(cells.len(), 2);
+        let (outer, inner) = if cells[0].shape.area() > cells[1].shape.area() {
+            (&cells[0], &cells[1])
+        } else {
+            (&cells[1], &cells[0])
+        };
+
+        assert_eq!(outer.shape.holes.len(), 1);
+        assert!(inner.shape.holes.is_empty());
+        assert!((outer.shape.area() - 12.0).abs() < 1e-5);
+        let center = Vec2::splat(2.0);
+        assert_eq!(outer.shape.locate(center), Containment::Outside);
+        assert_eq!(outer.shape.locate(Vec2::splat(0.5)), Containment::Inside);
+        assert_eq!(inner.shape.locate(center), Containment::Inside);
+        // The outer cell's centroid is in the hole, so its position has to be found elsewhere
+        assert_eq!(outer.shape.locate(outer.position), Containment::Inside);
+        assert!(inner.position.distance(center) < 1e-5);
+    }
 }
@@ -1,8 +1,20 @@
+pub mod analysis;
 pub mod basic_grid;
 pub mod board;
 pub mod custom_gizmos;
+pub mod error;
 pub mod export;
+pub mod format;
+pub mod graph;
+pub mod history;
 pub mod import;
 pub mod nav;
+pub mod notify;
+pub mod path;
+pub mod pathfinding;
+pub mod planar;
+pub mod project;
 pub mod rounding;
+pub mod spatial;
 pub mod util;
+pub mod validate;
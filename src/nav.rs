@@ -10,7 +10,8 @@ use bevy_egui::EguiContext;
 
 pub fn nav_plugin(app: &mut App) {
     app.add_event::<Pick>();
-    app.add_systems(PreUpdate, pick);
+    app.init_resource::<Cursor>();
+    app.add_systems(PreUpdate, (pick, update_cursor));
     app.add_systems(
         Update,
         (
@@ -90,6 +91,24 @@ fn zoom_camera(
     }
 }
 
+/// World position of the mouse, or `None` when it's outside the window or over the UI.
+#[derive(Resource, Default, Debug)]
+pub struct Cursor(pub Option<Vec2>);
+
+fn update_cursor(
+    ui: Query<&EguiContext, With<Window>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cursor: ResMut<Cursor>,
+) {
+    let (camera, camera_transform) = camera.single();
+    cursor.0 = windows
+        .single()
+        .cursor_position()
+        .filter(|_| !egui_blocking(ui.single()))
+        .and_then(|x| camera.viewport_to_world_2d(camera_transform, x));
+}
+
 #[derive(Event, Debug)]
 pub struct Pick {
     pub down: Vec2,
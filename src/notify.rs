@@ -0,0 +1,46 @@
+use std::fmt::Display;
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+pub fn notify_plugin(app: &mut App) {
+    app.init_resource::<Notifications>();
+    app.add_systems(Update, notifications_window);
+}
+
+/// Errors and warnings waiting to be shown to the user. Anything pushed here is displayed until
+/// it's dismissed.
+#[derive(Resource, Default, Debug)]
+pub struct Notifications(Vec<String>);
+
+impl Notifications {
+    pub fn push(&mut self, message: impl Display) {
+        self.0.push(message.to_string());
+    }
+}
+
+fn notifications_window(mut ui: EguiContexts, mut notifications: ResMut<Notifications>) {
+    if notifications.0.is_empty() {
+        return;
+    }
+    egui::Window::new("Notifications")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -8.0))
+        .collapsible(false)
+        .show(ui.ctx_mut(), |ui| {
+            let mut dismissed = None;
+            for (i, message) in notifications.0.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(egui::Color32::LIGHT_RED, message);
+                    if ui.small_button("✖").clicked() {
+                        dismissed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = dismissed {
+                notifications.0.remove(i);
+            }
+            if ui.button("Dismiss all").clicked() {
+                notifications.0.clear();
+            }
+        });
+}
@@ -0,0 +1,449 @@
+use std::{collections::BTreeMap, ops::Bound};
+
+use bevy::math::Vec2;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+/// How many straight pieces each segment between keyframes is split into when measuring arc
+/// length.
+const SAMPLES_PER_SEGMENT: usize = 32;
+
+/// A movement path between two cells. Keyframe times are usually in `0.0..=1.0`, but any
+/// increasing times work.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Path {
+    pub keyframes: BTreeMap<Keyframe, Vec2>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    /// Uniform Catmull-Rom spline through every keyframe.
+    CatmullRom,
+    /// Cubic Bézier segments between keyframes, shaped by tangent handles. Keyframes without
+    /// handles get zero-length ones.
+    CubicBezier(BTreeMap<Keyframe, Handles>),
+}
+
+/// Tangent handles of a keyframe, as offsets from its point.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub struct Handles {
+    pub incoming: Vec2,
+    pub outgoing: Vec2,
+}
+
+impl Path {
+    pub fn simple(start: Vec2, end: Vec2) -> Self {
+        Self {
+            keyframes: [(Keyframe(0.0), start), (Keyframe(1.0), end)].into(),
+            interpolation: Interpolation::Linear,
+        }
+    }
+
+    pub fn start(&self) -> Option<Vec2> {
+        self.keyframes.first_key_value().map(|(_, &x)| x)
+    }
+
+    pub fn end(&self) -> Option<Vec2> {
+        self.keyframes.last_key_value().map(|(_, &x)| x)
+    }
+
+    /// Times of the first and last keyframes.
+    pub fn domain(&self) -> Option<(f32, f32)> {
+        let first = self.keyframes.first_key_value()?.0 .0;
+        let last = self.keyframes.last_key_value()?.0 .0;
+        Some((first, last))
+    }
+
+    fn handles(&self, keyframe: Keyframe) -> Handles {
+        match &self.interpolation {
+            Interpolation::CubicBezier(handles) => {
+                handles.get(&keyframe).copied().unwrap_or_default()
+            }
+            _ => Handles::default(),
+        }
+    }
+
+    /// Position at time `t`, clamped to the keyframe range. Paths with no keyframes sit at the
+    /// origin.
+    pub fn sample(&self, t: f32) -> Vec2 {
+        let keyframes = &self.keyframes;
+        let (Some((&first, &p_first)), Some((&last, &p_last))) =
+            (keyframes.first_key_value(), keyframes.last_key_value())
+        else {
+            return Vec2::ZERO;
+        };
+        if t <= first.0 {
+            return p_first;
+        }
+        if t >= last.0 {
+            return p_last;
+        }
+        // Walking outward from `t` finds the keyframes on either side, then the ones past those
+        let mut before = keyframes.range(..=Keyframe(t));
+        let mut after = keyframes.range((Bound::Excluded(Keyframe(t)), Bound::Unbounded));
+        let (Some((&k1, &p1)), Some((&k2, &p2))) = (before.next_back(), after.next()) else {
+            // Only NaN isn't between the first and last keyframes by now
+            return p_last;
+        };
+        let u = (t - k1.0) / (k2.0 - k1.0);
+        match &self.interpolation {
+            Interpolation::Linear => p1.lerp(p2, u),
+            Interpolation::CatmullRom => {
+                let p0 = before.next_back().map_or(p1, |x| *x.1);
+                let p3 = after.next().map_or(p2, |x| *x.1);
+                catmull_rom(p0, p1, p2, p3, u)
+            }
+            Interpolation::CubicBezier(_) => {
+                let c1 = p1 + self.handles(k1).outgoing;
+                let c2 = p2 + self.handles(k2).incoming;
+                bezier(p1, c1, c2, p2, u)
+            }
+        }
+    }
+
+    /// Cumulative arc length at evenly spaced times, as `(t, length)` pairs starting at
+    /// `(first keyframe, 0.0)`.
+    pub fn arc_length_table(&self) -> Vec<(f32, f32)> {
+        let Some((first, last)) = self.domain() else {
+            return Vec::new();
+        };
+        let steps = (self.keyframes.len() - 1).max(1) * SAMPLES_PER_SEGMENT;
+        let points = (0..=steps)
+            .map(|i| first + (last - first) * i as f32 / steps as f32)
+            .map(|t| (t, self.sample(t)))
+            .collect::<Vec<_>>();
+        let mut length = 0.0;
+        let mut table = vec![(first, 0.0)];
+        for ((_, a), (t, b)) in points.into_iter().tuple_windows() {
+            length += a.distance(b);
+            table.push((t, length));
+        }
+        table
+    }
+
+    pub fn length(&self) -> f32 {
+        self.arc_length_table().last().map_or(0.0, |x| x.1)
+    }
+
+    /// Time at which the path has covered `distance`, for moving along it at constant speed.
+    pub fn time_at_distance(&self, distance: f32) -> f32 {
+        time_at_distance(&self.arc_length_table(), distance)
+    }
+
+    pub fn sample_at_distance(&self, distance: f32) -> Vec2 {
+        self.sample(self.time_at_distance(distance))
+    }
+
+    /// `count` points spaced evenly along the path by arc length, including both ends.
+    pub fn resample(&self, count: usize) -> Vec<Vec2> {
+        let table = self.arc_length_table();
+        let length = table.last().map_or(0.0, |x| x.1);
+        (0..count)
+            .map(|i| {
+                let fraction = if count > 1 {
+                    i as f32 / (count - 1) as f32
+                } else {
+                    0.0
+                };
+                self.sample(time_at_distance(&table, length * fraction))
+            })
+            .collect()
+    }
+
+    /// Every keyframe with the handles that make an equivalent Bézier curve.
+    fn bezier_keyframes(&self) -> Vec<(Keyframe, Vec2, Handles)> {
+        let keys = self
+            .keyframes
+            .iter()
+            .map(|(&k, &p)| (k, p))
+            .collect::<Vec<_>>();
+        (0..keys.len())
+            .map(|i| {
+                let (k, p) = keys[i];
+                let prev = i.checked_sub(1).map(|j| keys[j].1);
+                let next = keys.get(i + 1).map(|x| x.1);
+                let handles = match &self.interpolation {
+                    Interpolation::Linear => Handles {
+                        incoming: prev.map_or(Vec2::ZERO, |q| (q - p) / 3.0),
+                        outgoing: next.map_or(Vec2::ZERO, |q| (q - p) / 3.0),
+                    },
+                    Interpolation::CatmullRom => Handles {
+                        incoming: prev.map_or(Vec2::ZERO, |q| (q - next.unwrap_or(p)) / 6.0),
+                        outgoing: next.map_or(Vec2::ZERO, |q| (q - prev.unwrap_or(p)) / 6.0),
+                    },
+                    Interpolation::CubicBezier(_) => self.handles(k),
+                };
+                (k, p, handles)
+            })
+            .collect()
+    }
+
+    /// The same curve, with the same timing, as cubic Bézier segments.
+    pub fn to_bezier(&self) -> Self {
+        let mut keyframes = BTreeMap::new();
+        let mut handles = BTreeMap::new();
+        for (k, p, h) in self.bezier_keyframes() {
+            keyframes.insert(k, p);
+            handles.insert(k, h);
+        }
+        Self {
+            keyframes,
+            interpolation: Interpolation::CubicBezier(handles),
+        }
+    }
+
+    /// Joins `paths` end to end into one Bézier path, with path `i` moved to times `i..=i + 1`.
+    /// Where one path ends and the next starts becomes a single keyframe at the earlier path's end.
+    /// Paths without keyframes leave a gap in time.
+    pub fn concat<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Self {
+        let mut keyframes = BTreeMap::new();
+        let mut handles = BTreeMap::<Keyframe, Handles>::new();
+        for (i, path) in paths.into_iter().enumerate() {
+            let Some((first, last)) = path.domain() else {
+                continue;
+            };
+            for (k, p, h) in path.bezier_keyframes() {
+                let fraction = if last > first {
+                    (k.0 - first) / (last - first)
+                } else {
+                    0.0
+                };
+                let key = Keyframe(i as f32 + fraction);
+                if let Some(joint) = handles.get_mut(&key) {
+                    joint.outgoing = h.outgoing;
+                } else {
+                    keyframes.insert(key, p);
+                    handles.insert(key, h);
+                }
+            }
+        }
+        Self {
+            keyframes,
+            interpolation: Interpolation::CubicBezier(handles),
+        }
+    }
+
+    /// Moves the keyframe at `from` to time `to`, along with its Bézier handles. Does nothing if
+    /// there's no keyframe at `from` or there's already one at `to`.
+    pub fn retime(&mut self, from: Keyframe, to: Keyframe) {
+        if from == to || self.keyframes.contains_key(&to) {
+            return;
+        }
+        let Some(point) = self.keyframes.remove(&from) else {
+            return;
+        };
+        self.keyframes.insert(to, point);
+        if let Interpolation::CubicBezier(handles) = &mut self.interpolation {
+            if let Some(x) = handles.remove(&from) {
+                handles.insert(to, x);
+            }
+        }
+    }
+
+    /// Adds a keyframe at `at` where the curve already passes, between two existing keyframes.
+    /// Bézier segments are split in two so the curve keeps its shape. Returns `false` without
+    /// changing anything if there's already a keyframe at `at` or it's outside the path.
+    pub fn split(&mut self, at: Keyframe) -> bool {
+        if self.keyframes.contains_key(&at) {
+            return false;
+        }
+        let (Some((&k1, &p1)), Some((&k2, &p2))) = (
+            self.keyframes.range(..at).next_back(),
+            self.keyframes.range(at..).next(),
+        ) else {
+            return false;
+        };
+        let point = self.sample(at.0);
+        if let Interpolation::CubicBezier(handles) = &mut self.interpolation {
+            // de Casteljau at `u` gives the control points of both halves
+            let u = (at.0 - k1.0) / (k2.0 - k1.0);
+            let h1 = handles.get(&k1).copied().unwrap_or_default();
+            let h2 = handles.get(&k2).copied().unwrap_or_default();
+            let (c1, c2) = (p1 + h1.outgoing, p2 + h2.incoming);
+            let (a, b, c) = (p1.lerp(c1, u), c1.lerp(c2, u), c2.lerp(p2, u));
+            let (d, e) = (a.lerp(b, u), b.lerp(c, u));
+            handles.insert(
+                k1,
+                Handles {
+                    outgoing: a - p1,
+                    ..h1
+                },
+            );
+            handles.insert(
+                k2,
+                Handles {
+                    incoming: c - p2,
+                    ..h2
+                },
+            );
+            handles.insert(
+                at,
+                Handles {
+                    incoming: d - point,
+                    outgoing: e - point,
+                },
+            );
+        }
+        self.keyframes.insert(at, point);
+        true
+    }
+}
+
+fn time_at_distance(table: &[(f32, f32)], distance: f32) -> f32 {
+    let Some(&(first, _)) = table.first() else {
+        return 0.0;
+    };
+    if distance <= 0.0 {
+        return first;
+    }
+    let i = table.partition_point(|x| x.1 < distance);
+    if i >= table.len() {
+        return table[table.len() - 1].0;
+    }
+    let (t1, s1) = table[i - 1];
+    let (t2, s2) = table[i];
+    if s2 - s1 <= f32::EPSILON {
+        t2
+    } else {
+        t1 + (t2 - t1) * (distance - s1) / (s2 - s1)
+    }
+}
+
+fn catmull_rom(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, u: f32) -> Vec2 {
+    let (u2, u3) = (u * u, u * u * u);
+    0.5 * (2.0 * p1
+        + (p2 - p0) * u
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * u2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * u3)
+}
+
+fn bezier(p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, u: f32) -> Vec2 {
+    let v = 1.0 - u;
+    p0 * v * v * v + p1 * 3.0 * v * v * u + p2 * 3.0 * v * u * u + p3 * u * u * u
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct Keyframe(pub f32);
+
+impl Eq for Keyframe {}
+
+impl PartialOrd for Keyframe {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Keyframe {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_linear() {
+        let path = Path::simple(Vec2::ZERO, Vec2::new(4.0, 0.0));
+        assert_eq!(path.sample(0.25), Vec2::new(1.0, 0.0));
+        assert_eq!(path.sample(2.0), Vec2::new(4.0, 0.0));
+        assert!((path.length() - 4.0).abs() < 1e-4);
+        assert!((path.sample_at_distance(3.0) - Vec2::new(3.0, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn test_sample_between_keyframes() {
+        let mut path = Path::simple(Vec2::ZERO, Vec2::new(3.0, 0.0));
+        path.keyframes.insert(Keyframe(0.25), Vec2::new(1.0, 1.0));
+        path.keyframes.insert(Keyframe(0.5), Vec2::new(2.0, 1.0));
+        assert_eq!(path.sample(0.25), Vec2::new(1.0, 1.0));
+        assert_eq!(path.sample(0.375), Vec2::new(1.5, 1.0));
+        assert_eq!(path.sample(0.75), Vec2::new(2.5, 0.5));
+        assert_eq!(path.sample(-1.0), Vec2::ZERO);
+        assert_eq!(path.sample(f32::NAN), Vec2::new(3.0, 0.0));
+        assert_eq!(Path::concat([]).sample(0.5), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_curves_hit_keyframes() {
+        let mut path = Path::simple(Vec2::ZERO, Vec2::new(2.0, 0.0));
+        path.keyframes.insert(Keyframe(0.5), Vec2::new(1.0, 1.0));
+        for interpolation in [
+            Interpolation::CatmullRom,
+            Interpolation::CubicBezier([(Keyframe(0.5), Handles::default())].into()),
+        ] {
+            path.interpolation = interpolation;
+            assert!((path.sample(0.5) - Vec2::new(1.0, 1.0)).length() < 1e-5);
+            assert!(path.length() > 2.0);
+        }
+    }
+
+    #[test]
+    fn test_to_bezier() {
+        let mut path = Path::simple(Vec2::ZERO, Vec2::new(2.0, 0.0));
+        path.keyframes.insert(Keyframe(0.2), Vec2::new(1.0, 1.0));
+        path.keyframes.insert(Keyframe(0.7), Vec2::new(1.5, -1.0));
+        for interpolation in [Interpolation::Linear, Interpolation::CatmullRom] {
+            path.interpolation = interpolation;
+            let bezier = path.to_bezier();
+            for i in 0..=20 {
+                let t = i as f32 / 20.0;
+                assert!((path.sample(t) - bezier.sample(t)).length() < 1e-5, "{t}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        let (a, b, c) = (Vec2::ZERO, Vec2::new(1.0, 0.0), Vec2::new(1.0, 2.0));
+        let mut second = Path::simple(b, c);
+        second.keyframes.insert(Keyframe(0.5), Vec2::new(2.0, 1.0));
+        second.interpolation = Interpolation::CatmullRom;
+        let joined = Path::concat([&Path::simple(a, b), &second]);
+        assert_eq!(joined.keyframes.len(), 4);
+        assert_eq!(joined.domain(), Some((0.0, 2.0)));
+        assert!((joined.sample(0.5) - Vec2::new(0.5, 0.0)).length() < 1e-5);
+        assert!((joined.sample(1.0) - b).length() < 1e-5);
+        for i in 0..=10 {
+            let t = i as f32 / 10.0;
+            assert!((joined.sample(1.0 + t) - second.sample(t)).length() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_split() {
+        let mut path = Path::simple(Vec2::ZERO, Vec2::new(2.0, 0.0));
+        path.keyframes.insert(Keyframe(0.4), Vec2::new(1.0, 1.0));
+        let bezier = path.to_bezier();
+        let mut split = bezier.clone();
+        assert!(split.split(Keyframe(0.25)));
+        assert!(split.split(Keyframe(0.7)));
+        assert_eq!(split.keyframes.len(), 5);
+        for i in 0..=20 {
+            let t = i as f32 / 20.0;
+            assert!((split.sample(t) - bezier.sample(t)).length() < 1e-5, "{t}");
+        }
+        // Existing keyframes and times past the ends are left alone
+        assert!(!split.split(Keyframe(0.4)));
+        assert!(!split.split(Keyframe(1.5)));
+        assert_eq!(split.keyframes[&Keyframe(0.4)], Vec2::new(1.0, 1.0));
+        assert!(path.split(Keyframe(0.7)));
+        assert!((path.keyframes[&Keyframe(0.7)] - Vec2::new(1.5, 0.5)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_resample_even_spacing() {
+        let mut path = Path::simple(Vec2::ZERO, Vec2::new(10.0, 0.0));
+        // Squash most of the distance into the first tenth of the time
+        path.keyframes.insert(Keyframe(0.1), Vec2::new(9.0, 0.0));
+        let points = path.resample(11);
+        for (i, x) in points.iter().enumerate() {
+            assert!((x.x - i as f32).abs() < 0.05, "{points:?}");
+        }
+    }
+}
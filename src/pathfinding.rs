@@ -0,0 +1,321 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, VecDeque},
+};
+
+use bevy::utils::FloatOrd;
+
+use crate::{
+    board::{Board, Keyframe, Path},
+    graph::outgoing,
+    path::Interpolation,
+};
+
+/// What it costs to move along each link of a board. It's a snapshot: later changes to the board
+/// don't show up in it.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Costs {
+    /// Outgoing links of each cell and their costs, by ascending cell.
+    links: Vec<Vec<(usize, f32)>>,
+}
+
+impl Costs {
+    /// Every link costs the arc length of its path.
+    pub fn new(board: &Board) -> Self {
+        Self::from_fn(board, |_, _, path| path.length())
+    }
+
+    /// Every link costs 1, so the cheapest routes are those with the fewest links.
+    pub fn uniform(board: &Board) -> Self {
+        Self::from_fn(board, |_, _, _| 1.0)
+    }
+
+    /// Costs from `cost(from, to, path)`. Links to the same cell or to missing cells are left out.
+    pub fn from_fn(board: &Board, cost: impl Fn(usize, usize, &Path) -> f32) -> Self {
+        let links = outgoing(board)
+            .into_iter()
+            .enumerate()
+            .map(|(from, links)| {
+                let neighbors = &board.cells()[from].neighbors;
+                links
+                    .into_iter()
+                    .map(|to| (to, cost(from, to, &neighbors[&to])))
+                    .collect()
+            })
+            .collect();
+        Self { links }
+    }
+
+    pub fn get(&self, from: usize, to: usize) -> Option<f32> {
+        let links = self.links.get(from)?;
+        let i = links.binary_search_by_key(&to, |x| x.0).ok()?;
+        Some(links[i].1)
+    }
+
+    /// Overrides the cost of the link from `from` to `to`, if there is one. An infinite cost blocks
+    /// the link. Costs must not be negative.
+    pub fn set(&mut self, from: usize, to: usize, cost: f32) {
+        let Some(links) = self.links.get_mut(from) else {
+            return;
+        };
+        if let Ok(i) = links.binary_search_by_key(&to, |x| x.0) {
+            links[i].1 = cost;
+        }
+    }
+}
+
+/// A way through the board.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Route {
+    /// Every cell along the way, starting with where the route starts and ending with where it
+    /// ends.
+    pub cells: Vec<usize>,
+    pub cost: f32,
+}
+
+impl Route {
+    /// The paths of every link along the route, joined into one path where link `i` covers times
+    /// `i..=i + 1`. Routes that stay put get a single keyframe at the cell's position.
+    pub fn trajectory(&self, board: &Board) -> Path {
+        let legs = self
+            .cells
+            .windows(2)
+            .filter_map(|x| board.cells().get(x[0])?.neighbors.get(&x[1]))
+            .collect::<Vec<_>>();
+        if legs.is_empty() {
+            let cell = self.cells.first().and_then(|&x| board.cells().get(x));
+            return Path {
+                keyframes: cell
+                    .map(|x| (Keyframe(0.0), x.position))
+                    .into_iter()
+                    .collect(),
+                interpolation: Interpolation::Linear,
+            };
+        }
+        Path::concat(legs)
+    }
+}
+
+/// The route from `from` to `to` with the fewest links, ignoring costs. Its cost is the number of
+/// links.
+pub fn bfs(board: &Board, from: usize, to: usize) -> Option<Route> {
+    let outgoing = outgoing(board);
+    if from >= outgoing.len() || to >= outgoing.len() {
+        return None;
+    }
+    let mut previous = vec![None; outgoing.len()];
+    let mut visited = vec![false; outgoing.len()];
+    visited[from] = true;
+    let mut queue = VecDeque::from([from]);
+    while let Some(x) = queue.pop_front() {
+        if x == to {
+            let cells = walk_back(&previous, to);
+            let cost = (cells.len() - 1) as f32;
+            return Some(Route { cells, cost });
+        }
+        for &y in &outgoing[x] {
+            if !visited[y] {
+                visited[y] = true;
+                previous[y] = Some(x);
+                queue.push_back(y);
+            }
+        }
+    }
+    None
+}
+
+/// The cheapest route from `from` to `to`.
+pub fn dijkstra(costs: &Costs, from: usize, to: usize) -> Option<Route> {
+    a_star(costs, from, to, |_| 0.0)
+}
+
+/// Like [`dijkstra`], but searches toward `to` first, guided by `heuristic`'s estimate of the cost
+/// from a cell to `to`. The heuristic must never overestimate, or the route may not be the
+/// cheapest. [`straight_line`] is a good one for arc length costs.
+pub fn a_star(
+    costs: &Costs,
+    from: usize,
+    to: usize,
+    heuristic: impl Fn(usize) -> f32,
+) -> Option<Route> {
+    let search = search(costs, from, Some(to), f32::INFINITY, heuristic);
+    let cost = *search.cost.get(to)?;
+    cost.is_finite().then(|| Route {
+        cells: walk_back(&search.previous, to),
+        cost,
+    })
+}
+
+/// Distance between cell positions, which never overestimates the cost of links whose paths run
+/// between their cells' positions.
+pub fn straight_line(board: &Board, to: usize) -> impl Fn(usize) -> f32 + '_ {
+    let target = board.cells().get(to).map(|x| x.position);
+    move |x| match (board.cells().get(x), target) {
+        (Some(cell), Some(target)) => cell.position.distance(target),
+        _ => 0.0,
+    }
+}
+
+/// Every cell that can be reached from `from` for at most `budget`, with the cheapest cost of
+/// getting there. Includes `from` itself, at no cost.
+pub fn within_budget(costs: &Costs, from: usize, budget: f32) -> BTreeMap<usize, f32> {
+    let search = search(costs, from, None, budget, |_| 0.0);
+    search
+        .cost
+        .into_iter()
+        .enumerate()
+        .filter(|x| x.1.is_finite())
+        .collect()
+}
+
+struct Search {
+    /// Cheapest known cost of reaching each cell.
+    cost: Vec<f32>,
+    /// Where the cheapest known way of reaching each cell comes from.
+    previous: Vec<Option<usize>>,
+}
+
+/// A* from `from`, stopping once `to` is settled, and never going over `budget`.
+fn search(
+    costs: &Costs,
+    from: usize,
+    to: Option<usize>,
+    budget: f32,
+    heuristic: impl Fn(usize) -> f32,
+) -> Search {
+    let n = costs.links.len();
+    let mut search = Search {
+        cost: vec![f32::INFINITY; n],
+        previous: vec![None; n],
+    };
+    if from >= n {
+        return search;
+    }
+    search.cost[from] = 0.0;
+    let mut queue = BinaryHeap::from([(Reverse(FloatOrd(heuristic(from))), FloatOrd(0.0), from)]);
+    while let Some((_, FloatOrd(cost), x)) = queue.pop() {
+        // Skip entries left over from before a cheaper way was found
+        if cost > search.cost[x] {
+            continue;
+        }
+        if Some(x) == to {
+            break;
+        }
+        for &(y, link) in &costs.links[x] {
+            let next = cost + link;
+            if next < search.cost[y] && next <= budget {
+                search.cost[y] = next;
+                search.previous[y] = Some(x);
+                queue.push((Reverse(FloatOrd(next + heuristic(y))), FloatOrd(next), y));
+            }
+        }
+    }
+    search
+}
+
+fn walk_back(previous: &[Option<usize>], to: usize) -> Vec<usize> {
+    let mut cells = vec![to];
+    while let Some(x) = previous[*cells.last().unwrap()] {
+        cells.push(x);
+    }
+    cells.reverse();
+    cells
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bevy::math::Vec2;
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::board::{Cell, Polygon};
+
+    /// A `width` by `height` grid of unit squares, linked to their orthogonal neighbors by straight
+    /// paths.
+    fn grid(width: usize, height: usize) -> Board {
+        let position = |i: usize| Vec2::new((i % width) as f32, (i / width) as f32) + 0.5;
+        let cells = (0..width * height)
+            .map(|i| {
+                let neighbors = (0..width * height)
+                    .filter(|&j| position(i).distance(position(j)) == 1.0)
+                    .map(|j| (j, Path::simple(position(i), position(j))))
+                    .collect::<HashMap<_, _>>();
+                let corner = position(i) - 0.5;
+                let shape = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y].map(|x| x + corner);
+                Cell {
+                    neighbors,
+                    shape: Polygon::new(shape.to_vec()),
+                    position: position(i),
+                }
+            })
+            .collect();
+        Board::new(cells, Vec::new())
+    }
+
+    #[test]
+    fn test_routes() {
+        // 0 1 2
+        // 3 4 5
+        // 6 7 8
+        let board = grid(3, 3);
+        let mut costs = Costs::new(&board);
+        assert!(costs.get(0, 1).is_some_and(|x| (x - 1.0).abs() < 1e-5));
+        assert_eq!(costs.get(0, 4), None);
+
+        let route = bfs(&board, 0, 8).unwrap();
+        assert_eq!((route.cells.len(), route.cost), (5, 4.0));
+
+        // Make the middle expensive and the right column cheap
+        for x in [1, 3, 5, 7] {
+            costs.set(x, 4, 10.0);
+        }
+        costs.set(2, 5, 0.25);
+        costs.set(5, 8, 0.25);
+        let route = dijkstra(&costs, 0, 8).unwrap();
+        assert_eq!(route.cells, [0, 1, 2, 5, 8]);
+        assert!((route.cost - 2.5).abs() < 1e-4);
+        let heuristic = |x| straight_line(&board, 8)(x) * 0.25;
+        let guided = a_star(&costs, 0, 8, heuristic).unwrap();
+        assert_eq!(guided, route);
+
+        costs.set(5, 8, f32::INFINITY);
+        costs.set(7, 8, f32::INFINITY);
+        let route = dijkstra(&costs, 0, 8);
+        assert_eq!(route.map(|x| x.cells), None);
+        assert_eq!(dijkstra(&costs, 0, 0).map(|x| x.cells), Some(vec![0]));
+        assert_eq!(dijkstra(&costs, 0, 100), None);
+    }
+
+    #[test]
+    fn test_within_budget() {
+        let board = grid(4, 1);
+        let reachable = within_budget(&Costs::new(&board), 1, 1.5);
+        let reachable = reachable
+            .into_iter()
+            .map(|(x, cost)| (x, cost.round()))
+            .collect_vec();
+        assert_eq!(reachable, [(0, 1.0), (1, 0.0), (2, 1.0)]);
+    }
+
+    #[test]
+    fn test_trajectory() {
+        let board = grid(3, 2);
+        let route = dijkstra(&Costs::new(&board), 0, 5).unwrap();
+        let path = route.trajectory(&board);
+        assert_eq!(path.domain(), Some((0.0, 3.0)));
+        assert_eq!(path.start(), Some(board.cells()[0].position));
+        assert_eq!(path.end(), Some(board.cells()[5].position));
+        for (i, &x) in route.cells.iter().enumerate() {
+            assert!((path.sample(i as f32) - board.cells()[x].position).length() < 1e-5);
+        }
+        assert!((path.length() - route.cost).abs() < 1e-3);
+
+        let still = Route {
+            cells: vec![4],
+            cost: 0.0,
+        };
+        assert_eq!(still.trajectory(&board).keyframes.len(), 1);
+    }
+}
@@ -0,0 +1,292 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use bevy::{math::Vec2, utils::FloatOrd};
+
+use crate::util::MinMax;
+
+/// Faces of a planar line graph, found by walking a half-edge structure.
+#[derive(Debug, Clone, Default)]
+pub struct PlanarFaces {
+    /// Bounded faces, each a counter-clockwise ring of vertex indices.
+    pub faces: Vec<Vec<usize>>,
+    /// For each bounded face, the other bounded faces it shares an edge with.
+    pub neighbors: Vec<BTreeSet<usize>>,
+    /// Outer boundary of each connected component, as a clockwise ring of vertex indices.
+    pub boundaries: Vec<Vec<usize>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Problems found in the input graph. Neither stops extraction, but both mean the result probably
+/// isn't what the artist intended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// An edge that isn't part of any cycle. It's ignored when building faces.
+    DanglingEdge([usize; 2]),
+    /// Two edges that cross without sharing a vertex, so the graph isn't planar as drawn.
+    Crossing([usize; 2], [usize; 2]),
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::DanglingEdge([a, b]) => write!(f, "Dangling edge {a}-{b}"),
+            Diagnostic::Crossing([a, b], [c, d]) => {
+                write!(f, "Edges {a}-{b} and {c}-{d} cross without a shared vertex")
+            }
+        }
+    }
+}
+
+/// Finds every bounded face of the graph exactly once. Duplicate edges and self-loops are ignored,
+/// dangling edges are pruned, and each connected component's outer face is told apart from its
+/// bounded faces by winding (signed area).
+pub fn extract_faces(vertices: &[Vec2], lines: &[[usize; 2]]) -> PlanarFaces {
+    let mut diagnostics = Vec::new();
+    let edges = lines
+        .iter()
+        .filter(|[a, b]| a != b)
+        .map(|&[a, b]| {
+            let (a, b) = a.min_max(b);
+            [a, b]
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    diagnostics.extend(
+        crossings(vertices, &edges)
+            .into_iter()
+            .map(|(a, b)| Diagnostic::Crossing(edges[a], edges[b])),
+    );
+    let edges = prune_dangling(vertices.len(), edges, &mut diagnostics);
+
+    // Half-edge `2i` runs along `edges[i]`, and `2i + 1` is its twin running the other way
+    let origin = |h: usize| edges[h / 2][h % 2];
+    let target = |h: usize| origin(h ^ 1);
+    let mut outgoing = vec![Vec::new(); vertices.len()];
+    for h in 0..edges.len() * 2 {
+        outgoing[origin(h)].push(h);
+    }
+    let mut slot = vec![0; edges.len() * 2];
+    for list in &mut outgoing {
+        list.sort_by_key(|&h| {
+            let dir = vertices[target(h)] - vertices[origin(h)];
+            FloatOrd(dir.y.atan2(dir.x))
+        });
+        for (i, &h) in list.iter().enumerate() {
+            slot[h] = i;
+        }
+    }
+    // Leaving `h`'s target, take the outgoing edge just clockwise of the way we came in. This keeps
+    // the face being traced on our left.
+    let next = |h: usize| {
+        let twin = h ^ 1;
+        let around = &outgoing[origin(twin)];
+        around[(slot[twin] + around.len() - 1) % around.len()]
+    };
+
+    let mut face_of = vec![usize::MAX; edges.len() * 2];
+    let mut cycles = Vec::new();
+    for start in 0..edges.len() * 2 {
+        if face_of[start] != usize::MAX {
+            continue;
+        }
+        let mut ring = Vec::new();
+        let mut h = start;
+        loop {
+            face_of[h] = cycles.len();
+            ring.push(origin(h));
+            h = next(h);
+            if h == start {
+                break;
+            }
+        }
+        cycles.push(ring);
+    }
+
+    let mut faces = Vec::new();
+    let mut boundaries = Vec::new();
+    let mut face_index = Vec::with_capacity(cycles.len());
+    for ring in cycles {
+        if signed_area(vertices, &ring) > 0.0 {
+            face_index.push(Some(faces.len()));
+            faces.push(ring);
+        } else {
+            face_index.push(None);
+            boundaries.push(ring);
+        }
+    }
+
+    let mut neighbors = vec![BTreeSet::new(); faces.len()];
+    for e in 0..edges.len() {
+        let (a, b) = (face_index[face_of[e * 2]], face_index[face_of[e * 2 + 1]]);
+        if let (Some(a), Some(b)) = (a, b) {
+            if a != b {
+                neighbors[a].insert(b);
+                neighbors[b].insert(a);
+            }
+        }
+    }
+
+    PlanarFaces {
+        faces,
+        neighbors,
+        boundaries,
+        diagnostics,
+    }
+}
+
+/// Signed area of a ring of vertex indices, positive when the ring is counter-clockwise.
+pub fn signed_area(vertices: &[Vec2], ring: &[usize]) -> f32 {
+    let n = ring.len();
+    (0..n)
+        .map(|i| vertices[ring[i]].perp_dot(vertices[ring[(i + 1) % n]]))
+        .sum::<f32>()
+        / 2.0
+}
+
+/// Repeatedly removes edges hanging off degree-1 vertices until only cycles (and the paths
+/// between them) are left.
+fn prune_dangling(
+    vertex_count: usize,
+    edges: Vec<[usize; 2]>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Vec<[usize; 2]> {
+    let mut incident = vec![Vec::new(); vertex_count];
+    for (i, &[a, b]) in edges.iter().enumerate() {
+        incident[a].push(i);
+        incident[b].push(i);
+    }
+    let mut degree = incident.iter().map(Vec::len).collect::<Vec<_>>();
+    let mut removed = vec![false; edges.len()];
+    let mut stack = (0..vertex_count)
+        .filter(|&v| degree[v] == 1)
+        .collect::<Vec<_>>();
+    while let Some(v) = stack.pop() {
+        if degree[v] != 1 {
+            continue;
+        }
+        let Some(&e) = incident[v].iter().find(|&&e| !removed[e]) else {
+            continue;
+        };
+        removed[e] = true;
+        diagnostics.push(Diagnostic::DanglingEdge(edges[e]));
+        for x in edges[e] {
+            degree[x] -= 1;
+            if degree[x] == 1 {
+                stack.push(x);
+            }
+        }
+    }
+    edges
+        .into_iter()
+        .zip(removed)
+        .filter_map(|(e, removed)| (!removed).then_some(e))
+        .collect()
+}
+
+/// Pairs of edges (by index) that intersect anywhere other than a shared endpoint. Edges are swept
+/// left to right so only pairs with overlapping x extents get tested.
+fn crossings(vertices: &[Vec2], edges: &[[usize; 2]]) -> Vec<(usize, usize)> {
+    let extent = |[a, b]: [usize; 2]| {
+        let (a, b) = (vertices[a].x, vertices[b].x);
+        (a.min(b), a.max(b))
+    };
+    let mut order = (0..edges.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&e| FloatOrd(extent(edges[e]).0));
+    let mut result = Vec::new();
+    for (i, &e) in order.iter().enumerate() {
+        let max_x = extent(edges[e]).1;
+        for &f in &order[i + 1..] {
+            if extent(edges[f]).0 > max_x {
+                break;
+            }
+            let [a, b] = edges[e];
+            let [c, d] = edges[f];
+            if a == c || a == d || b == c || b == d {
+                continue;
+            }
+            let [a, b, c, d] = [a, b, c, d].map(|x| vertices[x]);
+            if segments_intersect(a, b, c, d) {
+                result.push((e.min(f), e.max(f)));
+            }
+        }
+    }
+    result
+}
+
+/// Whether segments `ab` and `cd` share any point, including where one just touches the other.
+pub fn segments_intersect(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let orient = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    let on_segment = |p: Vec2, q: Vec2, r: Vec2| {
+        r.x >= p.x.min(q.x) && r.x <= p.x.max(q.x) && r.y >= p.y.min(q.y) && r.y <= p.y.max(q.y)
+    };
+    let (d1, d2) = (orient(c, d, a), orient(c, d, b));
+    let (d3, d4) = (orient(a, b, c), orient(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+    (d1 == 0.0 && on_segment(c, d, a))
+        || (d2 == 0.0 && on_segment(c, d, b))
+        || (d3 == 0.0 && on_segment(a, b, c))
+        || (d4 == 0.0 && on_segment(a, b, d))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grid_2x1() -> (Vec<Vec2>, Vec<[usize; 2]>) {
+        let vertices = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(2.0, 0.0),
+            Vec2::new(2.0, 1.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+        ];
+        let lines = vec![[0, 1], [1, 2], [2, 3], [3, 4], [4, 5], [5, 0], [1, 4]];
+        (vertices, lines)
+    }
+
+    #[test]
+    fn test_adjacent_squares() {
+        let (vertices, lines) = grid_2x1();
+        let faces = extract_faces(&vertices, &lines);
+        assert_eq!(faces.faces.len(), 2);
+        assert_eq!(faces.boundaries.len(), 1);
+        assert!(faces.neighbors[0].contains(&1));
+        assert!(faces.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_islands_and_dangling_edges() {
+        let (mut vertices, mut lines) = grid_2x1();
+        // A second, disconnected square with a whisker hanging off it
+        vertices.extend([
+            Vec2::new(5.0, 0.0),
+            Vec2::new(6.0, 0.0),
+            Vec2::new(6.0, 1.0),
+            Vec2::new(5.0, 1.0),
+            Vec2::new(7.0, 2.0),
+        ]);
+        lines.extend([[6, 7], [7, 8], [8, 9], [9, 6], [8, 10]]);
+        let faces = extract_faces(&vertices, &lines);
+        assert_eq!(faces.faces.len(), 3);
+        assert_eq!(faces.boundaries.len(), 2);
+        assert_eq!(faces.diagnostics, [Diagnostic::DanglingEdge([8, 10])]);
+    }
+
+    #[test]
+    fn test_crossing() {
+        let vertices = vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+            Vec2::new(1.0, 0.0),
+        ];
+        let faces = extract_faces(&vertices, &[[0, 1], [2, 3]]);
+        assert!(faces
+            .diagnostics
+            .contains(&Diagnostic::Crossing([0, 1], [2, 3])));
+    }
+}
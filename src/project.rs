@@ -0,0 +1,91 @@
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    basic_grid::{hex, square, triangle, Layout},
+    error::{Error, Result},
+    format::GENERATOR,
+};
+
+/// Version of the grid project format written by this build.
+pub const PROJECT_VERSION: u32 = 1;
+
+/// An editable grid as saved by the grid builder. Unlike an exported board, this keeps the lattice
+/// so the grid can be reopened and edited.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProjectFile {
+    pub format_version: u32,
+    pub generator: String,
+    pub grid: ProjectGrid,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum ProjectGrid {
+    Square(Lattice<square::Cell>),
+    Hex(Lattice<hex::Cell>),
+    Triangle(Lattice<triangle::Cell>),
+}
+
+/// The cells that are switched on, and the edges that can only be crossed one way.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lattice<C> {
+    pub cells: Vec<C>,
+    /// `[from, to]` pairs.
+    pub one_way_edges: Vec<[C; 2]>,
+    /// `None` means the lattice's default layout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+    /// Whether cells that only share a corner are linked. Only square lattices have any.
+    #[serde(default)]
+    pub diagonals: bool,
+}
+
+impl ProjectFile {
+    pub fn new(grid: ProjectGrid) -> Self {
+        Self {
+            format_version: PROJECT_VERSION,
+            generator: GENERATOR.into(),
+            grid,
+        }
+    }
+
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let value: Value = serde_json::from_reader(reader)?;
+        let version = value["format_version"]
+            .as_u64()
+            .and_then(|x| u32::try_from(x).ok())
+            .unwrap_or(0);
+        if version > PROJECT_VERSION {
+            return Err(Error::UnsupportedVersion {
+                found: version,
+                supported: PROJECT_VERSION,
+            });
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let cells = vec![hex::Cell { q: 0, r: 0 }, hex::Cell { q: 1, r: 0 }];
+        let file = ProjectFile::new(ProjectGrid::Hex(Lattice {
+            one_way_edges: vec![[cells[0], cells[1]]],
+            cells,
+            layout: None,
+            diagonals: false,
+        }));
+        let json = file.to_json().unwrap();
+        assert_eq!(ProjectFile::from_reader(json.as_bytes()).unwrap(), file);
+    }
+}
@@ -0,0 +1,280 @@
+use bevy::math::{IVec2, Rect, UVec2, Vec2};
+
+use crate::board::{Cell, Containment};
+
+/// Buckets along either axis are capped so a single huge cell can't blow up memory.
+const MAX_BUCKETS_PER_AXIS: u32 = 1024;
+
+/// Uniform grid over the bounding boxes of a board's cells. Each bucket lists every cell whose
+/// bounding box touches it, in ascending cell order. Cells without finite bounds, like ones with
+/// no points, aren't in any bucket, so queries never return them.
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    bounds: Vec<Option<Rect>>,
+    origin: Vec2,
+    bucket_size: f32,
+    dims: UVec2,
+    buckets: Vec<Vec<usize>>,
+}
+
+impl SpatialIndex {
+    pub fn new(cells: &[Cell]) -> Self {
+        let bounds = cells
+            .iter()
+            .map(|x| {
+                let bounds = x.shape.bounding_box()?;
+                (bounds.min.is_finite() && bounds.max.is_finite()).then_some(bounds)
+            })
+            .collect::<Vec<_>>();
+        let Some(total) = bounds.iter().flatten().copied().reduce(|a, b| a.union(b)) else {
+            return Self {
+                bounds,
+                origin: Vec2::ZERO,
+                bucket_size: 1.0,
+                dims: UVec2::ZERO,
+                buckets: Vec::new(),
+            };
+        };
+        // Aim for roughly one cell per bucket
+        let sizes = bounds.iter().flatten().map(|x| x.size().max_element());
+        let mean_size = sizes.clone().sum::<f32>() / sizes.count() as f32;
+        let bucket_size = mean_size
+            .max(total.size().max_element() / (MAX_BUCKETS_PER_AXIS - 1) as f32)
+            .max(f32::EPSILON);
+        let dims = (total.size() / bucket_size).floor().as_uvec2() + UVec2::ONE;
+        let mut index = Self {
+            bounds,
+            origin: total.min,
+            bucket_size,
+            dims,
+            buckets: vec![Vec::new(); (dims.x * dims.y) as usize],
+        };
+        for i in 0..index.bounds.len() {
+            let Some(bounds) = index.bounds[i] else {
+                continue;
+            };
+            let (min, max) = index.bucket_range(bounds);
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let bucket = index.bucket_index(UVec2::new(x, y));
+                    index.buckets[bucket].push(i);
+                }
+            }
+        }
+        index
+    }
+
+    fn bucket_of(&self, pos: Vec2) -> IVec2 {
+        ((pos - self.origin) / self.bucket_size).floor().as_ivec2()
+    }
+
+    fn clamp(&self, bucket: IVec2) -> UVec2 {
+        bucket
+            .clamp(IVec2::ZERO, self.dims.as_ivec2() - IVec2::ONE)
+            .as_uvec2()
+    }
+
+    fn bucket_range(&self, rect: Rect) -> (UVec2, UVec2) {
+        (
+            self.clamp(self.bucket_of(rect.min)),
+            self.clamp(self.bucket_of(rect.max)),
+        )
+    }
+
+    fn bucket_index(&self, bucket: UVec2) -> usize {
+        (bucket.y * self.dims.x + bucket.x) as usize
+    }
+
+    fn in_bounds(&self, bucket: IVec2) -> bool {
+        bucket.cmpge(IVec2::ZERO).all() && bucket.cmplt(self.dims.as_ivec2()).all()
+    }
+
+    /// Cells whose bounding box might contain `pos`.
+    pub fn candidates(&self, pos: Vec2) -> &[usize] {
+        let bucket = self.bucket_of(pos);
+        if !self.in_bounds(bucket) {
+            return &[];
+        }
+        &self.buckets[self.bucket_index(bucket.as_uvec2())]
+    }
+
+    /// The first cell strictly containing `pos`.
+    pub fn pick(&self, cells: &[Cell], pos: Vec2) -> Option<usize> {
+        self.candidates(pos)
+            .iter()
+            .copied()
+            .find(|&i| cells[i].shape.locate(pos) == Containment::Inside)
+    }
+
+    /// Cells whose bounding box overlaps `rect`, in ascending order.
+    pub fn query_rect(&self, rect: Rect) -> Vec<usize> {
+        if self.buckets.is_empty() {
+            return Vec::new();
+        }
+        let (min, max) = self.bucket_range(rect);
+        let mut result = (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| UVec2::new(x, y)))
+            .flat_map(|x| &self.buckets[self.bucket_index(x)])
+            .copied()
+            .filter(|&i| self.bounds[i].is_some_and(|x| overlaps(x, rect)))
+            .collect::<Vec<_>>();
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    /// The cell whose shape is closest to `pos`, which is any cell containing it if there is one.
+    pub fn nearest(&self, cells: &[Cell], pos: Vec2) -> Option<usize> {
+        if self.buckets.is_empty() {
+            return None;
+        }
+        let center = self.clamp(self.bucket_of(pos)).as_ivec2();
+        let distance = |i: usize| (-cells[i].shape.signed_distance(pos)).max(0.0);
+        let mut best: Option<(usize, f32)> = None;
+        for radius in 0..self.dims.max_element() as i32 {
+            let ring = (-radius..=radius).flat_map(|y| {
+                (-radius..=radius)
+                    .filter(move |&x| x.abs() == radius || y.abs() == radius)
+                    .map(move |x| center + IVec2::new(x, y))
+            });
+            for bucket in ring.filter(|&x| self.in_bounds(x)) {
+                for &i in &self.buckets[self.bucket_index(bucket.as_uvec2())] {
+                    let d = distance(i);
+                    if !best.is_some_and(|(_, best)| d >= best) {
+                        best = Some((i, d));
+                    }
+                }
+            }
+            // Anything in a farther ring is at least `radius` buckets away
+            if best.is_some_and(|(_, d)| d <= radius as f32 * self.bucket_size) {
+                break;
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+}
+
+/// Like [`Rect::intersect`], but rects that only touch (or have no area) still count.
+fn overlaps(a: Rect, b: Rect) -> bool {
+    a.min.cmple(b.max).all() && b.min.cmple(a.max).all()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::board::{Board, Polygon};
+
+    fn cell(points: &[(f32, f32)]) -> Cell {
+        let points = points.iter().map(|&(x, y)| Vec2::new(x, y)).collect();
+        let shape = Polygon::new(points);
+        Cell {
+            neighbors: HashMap::new(),
+            position: shape.centroid(),
+            shape,
+        }
+    }
+
+    /// A 4 by 3 grid of unit squares, a triangle overlapping some of them, and a long thin strip
+    /// off to the side.
+    fn cells() -> Vec<Cell> {
+        let mut cells = (0..12)
+            .map(|i| {
+                let (x, y) = ((i % 4) as f32, (i / 4) as f32);
+                cell(&[(x, y), (x + 1.0, y), (x + 1.0, y + 1.0), (x, y + 1.0)])
+            })
+            .collect::<Vec<_>>();
+        cells.push(cell(&[(0.5, 0.5), (3.5, 0.5), (2.0, 2.5)]));
+        cells.push(cell(&[
+            (-10.0, 5.0),
+            (10.0, 5.0),
+            (10.0, 5.1),
+            (-10.0, 5.1),
+        ]));
+        cells
+    }
+
+    /// Points every quarter unit, so plenty land on shared edges and corners.
+    fn samples() -> impl Iterator<Item = Vec2> {
+        (-48..=48).flat_map(|x| (-8..=32).map(move |y| Vec2::new(x as f32, y as f32) * 0.25))
+    }
+
+    #[test]
+    fn test_query_rect() {
+        let cells = cells();
+        let index = SpatialIndex::new(&cells);
+        for min in samples().step_by(7) {
+            for size in [
+                Vec2::ZERO,
+                Vec2::splat(0.3),
+                Vec2::new(2.5, 0.5),
+                Vec2::splat(30.0),
+            ] {
+                let rect = Rect::from_corners(min, min + size);
+                let expected = (0..cells.len())
+                    .filter(|&i| overlaps(cells[i].shape.bounding_box().unwrap(), rect))
+                    .collect::<Vec<_>>();
+                assert_eq!(index.query_rect(rect), expected, "{rect:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        let cells = cells();
+        let index = SpatialIndex::new(&cells);
+        for pos in samples() {
+            let expected =
+                (0..cells.len()).find(|&i| cells[i].shape.locate(pos) == Containment::Inside);
+            assert_eq!(index.pick(&cells, pos), expected, "{pos}");
+        }
+        // On the edge between two squares, neither contains the point
+        assert_eq!(index.pick(&cells, Vec2::new(1.0, 2.5)), None);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let cells = cells();
+        let index = SpatialIndex::new(&cells);
+        let distance = |i: usize, pos: Vec2| (-cells[i].shape.signed_distance(pos)).max(0.0);
+        // Includes points far outside the index, where the search has to widen many rings
+        for pos in samples().chain([Vec2::new(40.0, -30.0), Vec2::new(0.0, 60.0)]) {
+            let expected = (0..cells.len())
+                .map(|i| distance(i, pos))
+                .fold(f32::INFINITY, f32::min);
+            let found = index.nearest(&cells, pos).unwrap();
+            assert!((distance(found, pos) - expected).abs() < 1e-5, "{pos}");
+        }
+    }
+
+    #[test]
+    fn test_bucket_cap() {
+        // One huge cell next to tiny ones would want millions of buckets
+        let mut cells = (0..2000)
+            .map(|i| {
+                let x = i as f32 * 0.001;
+                cell(&[(x, 0.0), (x + 0.001, 0.0), (x + 0.001, 0.001), (x, 0.001)])
+            })
+            .collect::<Vec<_>>();
+        cells.push(cell(&[(0.0, 1.0), (1e4, 1.0), (1e4, 1e4), (0.0, 1e4)]));
+        let index = SpatialIndex::new(&cells);
+        assert!(index.dims.max_element() <= MAX_BUCKETS_PER_AXIS);
+        assert_eq!(index.pick(&cells, Vec2::new(0.0055, 0.0005)), Some(5));
+        assert_eq!(index.pick(&cells, Vec2::new(5e3, 5e3)), Some(2000));
+        assert_eq!(
+            index.query_rect(Rect::new(0.0, 0.0, 0.0025, 0.0005)),
+            [0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut board = Board::new(cells(), Vec::new());
+        let pos = Vec2::new(20.0, 20.0);
+        assert_eq!(board.pick(pos), None);
+        let moved = board.cells()[0].shape.clone() + Vec2::splat(19.5);
+        board.cells_mut()[0].shape = moved;
+        assert_eq!(board.pick(pos), Some(0));
+    }
+}
@@ -0,0 +1,539 @@
+use std::fmt::{self, Display};
+
+use bevy::math::Vec2;
+use itertools::Itertools;
+
+use crate::{
+    board::{Board, Cell, Containment, Mesh, Polygon},
+    planar::segments_intersect,
+};
+
+/// How far path ends may be from the positions of the cells they join.
+const PATH_END_TOLERANCE: f32 = 1e-3;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Severity {
+    /// Probably a mistake, but the board still works.
+    Warning,
+    /// Anything reading the board may misbehave or crash.
+    Error,
+}
+
+/// What a [`Diagnostic`] is about, by index into the board's cells or meshes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Subject {
+    Cell(usize),
+    Mesh(usize),
+}
+
+/// A problem found by [`validate`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub subject: Subject,
+    pub message: String,
+    /// How to fix it, if there's an obvious way.
+    pub fix: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, subject: Subject, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            subject,
+            message: message.into(),
+            fix: None,
+        }
+    }
+
+    fn fix(self, fix: impl Into<String>) -> Self {
+        Self {
+            fix: Some(fix.into()),
+            ..self
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Subject::Cell(i) => write!(f, "cell {i}"),
+            Subject::Mesh(i) => write!(f, "mesh {i}"),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.subject, self.message)?;
+        if let Some(fix) = &self.fix {
+            write!(f, " ({fix})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything wrong with `board`, errors first, then in cell and mesh order.
+pub fn validate(board: &Board) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+    for (i, cell) in board.cells().iter().enumerate() {
+        check_cell(board, i, cell, &mut out);
+    }
+    check_overlaps(board, &mut out);
+    for (i, mesh) in board.meshes.iter().enumerate() {
+        check_mesh(i, &mesh.mesh, &mut out);
+    }
+    out.sort_by_key(|x| std::cmp::Reverse(x.severity));
+    out
+}
+
+fn is_valid_shape(cell: &Cell) -> bool {
+    cell.shape.rings().all(|x| x.len() >= 3)
+        && cell.position.is_finite()
+        && cell.shape.rings().flatten().all(|x| x.is_finite())
+}
+
+fn check_cell(board: &Board, i: usize, cell: &Cell, out: &mut Vec<Diagnostic>) {
+    use Severity::*;
+    let subject = Subject::Cell(i);
+
+    for &n in cell.neighbors.keys().sorted() {
+        let path = &cell.neighbors[&n];
+        if n == i {
+            out.push(Diagnostic::new(Error, subject, "Links to itself").fix("Remove the link"));
+        } else if n >= board.cells().len() {
+            out.push(
+                Diagnostic::new(Error, subject, format!("Links to missing cell {n}"))
+                    .fix("Remove the link"),
+            );
+        } else if let (Some(start), Some(end)) = (path.start(), path.end()) {
+            if start.distance(cell.position) > PATH_END_TOLERANCE {
+                out.push(
+                    Diagnostic::new(
+                        Warning,
+                        subject,
+                        format!("Path to cell {n} doesn't start at this cell"),
+                    )
+                    .fix("Move its first keyframe to this cell's position"),
+                );
+            }
+            if end.distance(board.cells()[n].position) > PATH_END_TOLERANCE {
+                out.push(
+                    Diagnostic::new(
+                        Warning,
+                        subject,
+                        format!("Path to cell {n} doesn't end at that cell"),
+                    )
+                    .fix(format!("Move its last keyframe to cell {n}'s position")),
+                );
+            }
+        } else {
+            out.push(
+                Diagnostic::new(Error, subject, format!("Path to cell {n} has no keyframes"))
+                    .fix("Reset it to a straight line"),
+            );
+        }
+    }
+
+    if cell.shape.points.len() < 3 {
+        out.push(
+            Diagnostic::new(Error, subject, "Shape has fewer than 3 corners")
+                .fix("Add corners or delete the cell"),
+        );
+        return;
+    }
+    if let Some(h) = cell.shape.holes.iter().position(|x| x.len() < 3) {
+        out.push(
+            Diagnostic::new(Error, subject, format!("Hole {h} has fewer than 3 corners"))
+                .fix("Add corners or delete the hole"),
+        );
+        return;
+    }
+    if !is_valid_shape(cell) {
+        out.push(Diagnostic::new(
+            Error,
+            subject,
+            "Has coordinates that aren't finite numbers",
+        ));
+        return;
+    }
+    if cell.shape.rings().any(ring_intersects_itself) {
+        out.push(
+            Diagnostic::new(Error, subject, "Shape intersects itself")
+                .fix("Move its corners so no two sides cross"),
+        );
+        return;
+    }
+    let outline = Polygon::new(cell.shape.points.clone());
+    let mut broken_hole = false;
+    for (h, hole) in cell.shape.holes.iter().enumerate() {
+        let message = if sides(hole)
+            .cartesian_product(sides(&outline.points))
+            .any(|((a, b), (c, d))| segments_intersect(a, b, c, d))
+        {
+            format!("Hole {h} crosses the outline")
+        } else if hole
+            .iter()
+            .any(|&x| outline.locate(x) == Containment::Outside)
+        {
+            format!("Hole {h} is outside the outline")
+        } else {
+            continue;
+        };
+        out.push(
+            Diagnostic::new(Error, subject, message)
+                .fix("Move the hole's corners inside the outline, clear of its sides"),
+        );
+        broken_hole = true;
+    }
+    if broken_hole {
+        return;
+    }
+    if !cell.shape.is_ccw() {
+        out.push(
+            Diagnostic::new(Warning, subject, "Shape winds clockwise")
+                .fix("Reverse the order of its corners"),
+        );
+    }
+    if cell.shape.locate(cell.position) != Containment::Inside {
+        out.push(
+            Diagnostic::new(Warning, subject, "Position is outside the shape")
+                .fix("Move it inside, e.g. to the shape's centroid"),
+        );
+    }
+}
+
+/// The sides of `ring`, as pairs of corners.
+fn sides(ring: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + Clone + '_ {
+    ring.iter().circular_tuple_windows().map(|(&a, &b)| (a, b))
+}
+
+/// Whether any two sides of `ring` touch, other than neighboring sides at their shared corner.
+fn ring_intersects_itself(ring: &[Vec2]) -> bool {
+    let sides = sides(ring).collect::<Vec<_>>();
+    let n = sides.len();
+    (0..n).tuple_combinations().any(|(i, j)| {
+        let adjacent = j == i + 1 || (i == 0 && j == n - 1);
+        let ((a, b), (c, d)) = (sides[i], sides[j]);
+        !adjacent && segments_intersect(a, b, c, d)
+    })
+}
+
+fn check_overlaps(board: &Board, out: &mut Vec<Diagnostic>) {
+    for (i, cell) in board.cells().iter().enumerate() {
+        // Broken shapes are reported already
+        if !is_valid_shape(cell) {
+            continue;
+        }
+        let Some(bounds) = cell.shape.bounding_box() else {
+            continue;
+        };
+        for j in board.query_rect(bounds) {
+            let other = &board.cells()[j];
+            if j > i && is_valid_shape(other) && shapes_overlap(&cell.shape, &other.shape) {
+                out.push(
+                    Diagnostic::new(
+                        Severity::Warning,
+                        Subject::Cell(i),
+                        format!("Overlaps cell {j}"),
+                    )
+                    .fix("Move their corners so they only share sides"),
+                );
+            }
+        }
+    }
+}
+
+/// Whether the insides of `a` and `b` overlap. Shapes that only share sides or corners don't.
+fn shapes_overlap(a: &Polygon, b: &Polygon) -> bool {
+    let sides = |x: &Polygon| x.rings().flat_map(sides).collect::<Vec<_>>();
+    // Points just inside each side catch overlaps where every corner lies on the other shape's
+    // boundary, like identical or half-shifted shapes
+    let side_inside = |x: &Polygon, y: &Polygon| {
+        sides(x).into_iter().any(|(p, q)| {
+            let nudge = (q - p).perp() * 1e-3;
+            [0.25, 0.5, 0.75]
+                .into_iter()
+                .flat_map(|f| [p.lerp(q, f) + nudge, p.lerp(q, f) - nudge])
+                .any(|s| x.locate(s) == Containment::Inside && y.locate(s) == Containment::Inside)
+        })
+    };
+    let corner_inside = |x: &Polygon, y: &Polygon| {
+        x.rings()
+            .flatten()
+            .any(|&p| y.locate(p) == Containment::Inside)
+    };
+    let sides_cross = sides(a)
+        .into_iter()
+        .cartesian_product(sides(b))
+        .any(|((p, q), (r, s))| segments_cross(p, q, r, s));
+    sides_cross
+        || corner_inside(a, b)
+        || corner_inside(b, a)
+        || side_inside(a, b)
+        || side_inside(b, a)
+}
+
+/// Whether `ab` and `cd` cross at a point inside both.
+fn segments_cross(a: Vec2, b: Vec2, c: Vec2, d: Vec2) -> bool {
+    let orient = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    orient(c, d, a) * orient(c, d, b) < 0.0 && orient(a, b, c) * orient(a, b, d) < 0.0
+}
+
+fn check_mesh(i: usize, mesh: &Mesh, out: &mut Vec<Diagnostic>) {
+    use Severity::*;
+    let subject = Subject::Mesh(i);
+    let (vertices, primitives, kind) = match mesh {
+        Mesh::IndexedLineMesh { vertices, lines } => (
+            vertices,
+            lines.iter().map(|x| &x[..]).collect_vec(),
+            "lines",
+        ),
+        Mesh::IndexedTriMesh {
+            vertices,
+            triangles,
+        } => (
+            vertices,
+            triangles.iter().map(|x| &x[..]).collect_vec(),
+            "triangles",
+        ),
+    };
+    if !vertices.iter().all(|x| x.is_finite()) {
+        out.push(Diagnostic::new(
+            Error,
+            subject,
+            "Has vertices that aren't finite numbers",
+        ));
+    }
+    if let Some(x) = primitives
+        .iter()
+        .copied()
+        .flatten()
+        .find(|&&x| x >= vertices.len())
+    {
+        out.push(
+            Diagnostic::new(
+                Error,
+                subject,
+                format!("Uses vertex {x}, but only has {}", vertices.len()),
+            )
+            .fix(format!("Remove the {kind} that use missing vertices")),
+        );
+    }
+    let degenerate = primitives.iter().filter(|x| !x.iter().all_unique()).count();
+    if degenerate > 0 {
+        out.push(
+            Diagnostic::new(
+                Warning,
+                subject,
+                format!("Has {degenerate} {kind} that repeat a vertex"),
+            )
+            .fix(format!("Remove those {kind}")),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use bevy::math::Vec3;
+
+    use super::*;
+    use crate::board::{BoardColor, BoardMesh, Path};
+
+    fn square(x: f32) -> Cell {
+        let points = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+            .map(|(a, b)| Vec2::new(a + x, b))
+            .to_vec();
+        Cell {
+            neighbors: HashMap::new(),
+            shape: Polygon::new(points),
+            position: Vec2::new(x + 0.5, 0.5),
+        }
+    }
+
+    fn linked(mut cells: Vec<Cell>) -> Vec<Cell> {
+        let positions = cells.iter().map(|x| x.position).collect_vec();
+        for (i, cell) in cells.iter_mut().enumerate() {
+            for (j, &p) in positions.iter().enumerate() {
+                if i != j && cell.position.distance(p) < 1.5 {
+                    cell.neighbors.insert(j, Path::simple(cell.position, p));
+                }
+            }
+        }
+        cells
+    }
+
+    fn has(diagnostics: &[Diagnostic], severity: Severity, subject: Subject, text: &str) -> bool {
+        diagnostics
+            .iter()
+            .any(|x| x.severity == severity && x.subject == subject && x.message.contains(text))
+    }
+
+    #[test]
+    fn test_clean_board() {
+        let board = Board::new(linked(vec![square(0.0), square(1.0), square(2.0)]), vec![]);
+        assert_eq!(validate(&board), vec![]);
+    }
+
+    #[test]
+    fn test_cell_problems() {
+        let mut cells = linked(vec![square(0.0), square(1.0), square(0.5), square(3.0)]);
+        cells[0]
+            .neighbors
+            .insert(0, Path::simple(Vec2::ZERO, Vec2::ZERO));
+        cells[0]
+            .neighbors
+            .insert(9, Path::simple(Vec2::ZERO, Vec2::ZERO));
+        cells[1].neighbors.get_mut(&0).unwrap().keyframes.clear();
+        cells[1].shape.points.reverse();
+        cells[2]
+            .neighbors
+            .insert(3, Path::simple(Vec2::new(1.0, 0.5), Vec2::new(3.5, 0.5)));
+        cells[3].shape.points.swap(0, 1);
+        cells[3].position = Vec2::new(-5.0, 0.0);
+        let diagnostics = validate(&Board::new(cells, vec![]));
+
+        use Severity::*;
+        assert!(has(&diagnostics, Error, Subject::Cell(0), "itself"));
+        assert!(has(&diagnostics, Error, Subject::Cell(0), "missing cell 9"));
+        assert!(has(&diagnostics, Error, Subject::Cell(1), "no keyframes"));
+        assert!(has(&diagnostics, Warning, Subject::Cell(1), "clockwise"));
+        assert!(has(
+            &diagnostics,
+            Warning,
+            Subject::Cell(0),
+            "Overlaps cell 2"
+        ));
+        assert!(has(
+            &diagnostics,
+            Warning,
+            Subject::Cell(1),
+            "Overlaps cell 2"
+        ));
+        assert!(!has(
+            &diagnostics,
+            Warning,
+            Subject::Cell(0),
+            "Overlaps cell 1"
+        ));
+        assert!(has(
+            &diagnostics,
+            Error,
+            Subject::Cell(3),
+            "intersects itself"
+        ));
+        assert!(has(&diagnostics, Warning, Subject::Cell(2), "doesn't end"));
+        assert!(diagnostics
+            .iter()
+            .tuple_windows()
+            .all(|(a, b)| a.severity >= b.severity));
+
+        // A broken cell doesn't stop overlaps between the others from being found
+        let mut cells = vec![square(0.0), square(0.5), square(3.0)];
+        cells[2].shape.points.clear();
+        let diagnostics = validate(&Board::new(cells, vec![]));
+        assert!(has(&diagnostics, Error, Subject::Cell(2), "fewer than 3"));
+        assert!(has(
+            &diagnostics,
+            Warning,
+            Subject::Cell(0),
+            "Overlaps cell 1"
+        ));
+
+        // Holes need 3 corners and must sit inside the outline without crossing it
+        let hole = |points: &[(f32, f32)]| points.iter().map(|&(x, y)| Vec2::new(x, y)).collect();
+        let mut cells = vec![square(0.0), square(2.0), square(4.0), square(6.0)];
+        cells[0].shape.holes.push(hole(&[(0.4, 0.4), (0.6, 0.4)]));
+        cells[1]
+            .shape
+            .holes
+            .push(hole(&[(2.4, 0.4), (3.4, 0.4), (3.4, 0.6)]));
+        cells[2]
+            .shape
+            .holes
+            .push(hole(&[(4.2, 0.2), (4.2, 0.4), (4.4, 0.4)]));
+        cells[2]
+            .shape
+            .holes
+            .push(hole(&[(5.5, 0.2), (5.5, 0.4), (5.7, 0.4)]));
+        cells[3]
+            .shape
+            .holes
+            .push(hole(&[(6.2, 0.2), (6.2, 0.4), (6.4, 0.4)]));
+        let diagnostics = validate(&Board::new(cells, vec![]));
+        assert!(has(
+            &diagnostics,
+            Error,
+            Subject::Cell(0),
+            "Hole 0 has fewer than 3"
+        ));
+        assert!(has(&diagnostics, Error, Subject::Cell(1), "Hole 0 crosses"));
+        assert!(has(
+            &diagnostics,
+            Error,
+            Subject::Cell(2),
+            "Hole 1 is outside"
+        ));
+        assert!(!has(&diagnostics, Error, Subject::Cell(2), "Hole 0"));
+        assert!(!diagnostics.iter().any(|x| x.subject == Subject::Cell(3)));
+    }
+
+    #[test]
+    fn test_mesh_problems() {
+        let mesh = Mesh::IndexedTriMesh {
+            vertices: vec![Vec3::ZERO, Vec3::X, Vec3::Y],
+            triangles: vec![[0, 1, 2], [0, 0, 1], [1, 2, 3]],
+        };
+        let board = Board::new(
+            vec![square(0.0)],
+            vec![BoardMesh {
+                color: BoardColor::PlayerColor,
+                mesh,
+            }],
+        );
+        let diagnostics = validate(&board);
+        assert!(has(
+            &diagnostics,
+            Severity::Error,
+            Subject::Mesh(0),
+            "vertex 3"
+        ));
+        assert!(has(
+            &diagnostics,
+            Severity::Warning,
+            Subject::Mesh(0),
+            "1 triangles"
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut cells = vec![square(0.0), square(1.0)];
+        let (a, b) = (cells[0].position, cells[1].position);
+        cells[0].neighbors.insert(1, Path::simple(a, b));
+        cells[1].neighbors.insert(1, Path::simple(b, b));
+        cells[1].neighbors.insert(5, Path::simple(b, a));
+        let lines = validate(&Board::new(cells, vec![]))
+            .iter()
+            .map(|x| x.to_string())
+            .collect_vec();
+        assert_eq!(
+            lines,
+            [
+                "error: cell 1: Links to itself (Remove the link)",
+                "error: cell 1: Links to missing cell 5 (Remove the link)"
+            ]
+        );
+    }
+}